@@ -0,0 +1,70 @@
+use crate::CLI;
+use crate::Maybe;
+use crate::alias::Aliases;
+use crate::source::SourcePHP;
+use anyhow::{bail, ensure};
+use clap::Args;
+
+#[derive(Args, Clone, Debug)]
+pub struct AliasArgs {
+  /// Alias name, e.g. `prod`
+  #[arg(required_unless_present = "list")]
+  name: Option<String>,
+
+  /// Installed version (or another alias) the name refers to
+  #[arg(required_unless_present = "list")]
+  version: Option<String>,
+
+  /// List every alias
+  #[arg(long, default_value_t = false, conflicts_with_all = ["name", "version"])]
+  list: bool,
+}
+
+#[derive(Args, Clone, Debug)]
+pub struct UnaliasArgs {
+  /// Alias name to remove
+  name: String,
+}
+
+impl AliasArgs {
+  pub async fn handle(&self) -> Maybe<()> {
+    let mut aliases = Aliases::load(CLI.aliases_file())?;
+
+    let (Some(name), Some(version)) = (self.name.as_deref(), self.version.as_deref()) else {
+      for (name, version) in aliases.iter() {
+        let missing = if SourcePHP::new(CLI.archives().join(version)).is_installed() {
+          ""
+        } else {
+          " (not installed)"
+        };
+        println!("{name} -> {version}{missing}");
+      }
+      return Ok(());
+    };
+
+    Aliases::validate(name)?;
+    ensure!(!CLI.archives().join(name).exists(), "`{name}` is already an installed version");
+
+    let source = SourcePHP::new(CLI.resolve(version)?);
+    ensure!(source.is_installed(), "Version `{version}` is not installed");
+
+    match aliases.set(name, source.name()) {
+      Some(previous) => println!("{name} -> {} (was {previous})", source.name()),
+      None => println!("{name} -> {}", source.name()),
+    }
+    aliases.save(CLI.aliases_file())
+  }
+}
+
+impl UnaliasArgs {
+  pub async fn handle(&self) -> Maybe<()> {
+    let mut aliases = Aliases::load(CLI.aliases_file())?;
+    let Some(version) = aliases.remove(&self.name) else {
+      bail!("No alias named `{}`", self.name);
+    };
+
+    aliases.save(CLI.aliases_file())?;
+    println!("Removed alias {} -> {version}", self.name);
+    Ok(())
+  }
+}
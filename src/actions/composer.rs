@@ -0,0 +1,164 @@
+use crate::CLI;
+use crate::Maybe;
+use crate::parse_index_url;
+use crate::progress::Reporter;
+use crate::repository::sha256;
+use crate::source::SourcePHP;
+use crate::static_const::COMPOSER_URL;
+use anyhow::{Context, bail, ensure};
+use clap::{Args, Subcommand};
+use reqwest::Url;
+use std::process::Stdio;
+
+#[derive(Args, Clone, Debug)]
+pub struct ComposerArgs {
+  #[command(subcommand)]
+  command: ComposerCommand,
+
+  /// Where `composer.phar` releases are downloaded from
+  #[arg(long, env = "MAPHP_COMPOSER_URL", global = true, default_value = COMPOSER_URL, value_parser = parse_index_url)]
+  composer_url: Url,
+}
+
+#[derive(Subcommand, Clone, Debug)]
+enum ComposerCommand {
+  /// Download composer.phar into a version's `dist/bin` with a `composer` wrapper running that PHP
+  Install {
+    /// Composer release: `stable`, `preview`, a major/minor line such as `2.x` or `2.2.x`, or an exact version
+    #[arg(long, default_value = "stable")]
+    version: String,
+
+    /// Installed PHP version or alias, defaults to the active one
+    #[arg(long)]
+    php: Option<String>,
+  },
+  /// Run `composer self-update` for every version with Composer installed
+  SelfUpdate {
+    /// Only update Composer of this PHP version or alias
+    #[arg(long)]
+    php: Option<String>,
+  },
+}
+
+impl ComposerArgs {
+  pub async fn handle(&self) -> Maybe<()> {
+    match self.command {
+      ComposerCommand::Install { ref version, ref php } => self.install(version, CLI.target(php.as_deref())?).await,
+      ComposerCommand::SelfUpdate { ref php } => {
+        let sources = match php {
+          Some(php) => vec![CLI.target(Some(php))?],
+          None => SourcePHP::scan_local(CLI.archives())?.into_iter().filter(has_composer).collect(),
+        };
+        ensure!(
+          !sources.is_empty(),
+          "Composer isn't installed for any version, run `maphp composer install`"
+        );
+
+        let mut failed = vec![];
+        for source in &sources {
+          if let Err(err) = self_update(source) {
+            println!("❌ {}: {err:#}", source.name());
+            failed.push(source.name().into_owned());
+          }
+        }
+        ensure!(failed.is_empty(), "Couldn't update Composer of {}", failed.join(", "));
+        Ok(())
+      }
+    }
+  }
+
+  async fn install(&self, version: &str, source: SourcePHP) -> Maybe<()> {
+    let release = release_path(version);
+    let phar_url = self.composer_url.join(&format!("{release}/composer.phar"))?;
+    let checksum_url = self.composer_url.join(&format!("{release}/composer.phar.sha256sum"))?;
+    let client = reqwest::Client::builder().user_agent(crate::static_const::USER_AGENT).build()?;
+
+    let spinner = Reporter::spinner(CLI.manager().options().progress);
+    spinner.set_prefix(format!("Downloading Composer {version} for PHP {}", source.name()));
+    let checksum = client
+      .get(checksum_url.clone())
+      .send()
+      .await?
+      .error_for_status()
+      .with_context(|| format!("No Composer release `{version}`"))?
+      .text()
+      .await?;
+    let Some(expected) = checksum.split_whitespace().next() else {
+      bail!("Empty checksum at {checksum_url}")
+    };
+    let phar = client.get(phar_url.clone()).send().await?.error_for_status()?.bytes().await?;
+
+    let actual = sha256(phar.as_ref())?;
+    ensure!(
+      actual.eq_ignore_ascii_case(expected),
+      "Checksum mismatch for {phar_url}: expected {expected}, got {actual}"
+    );
+
+    let bin = source.dist().join("bin");
+    crate::disk::atomic_write(bin.join("composer.phar"), &phar)?;
+    let wrapper = format!(
+      "#!/bin/sh\nexec \"{}\" \"{}\" \"$@\"\n",
+      bin.join("php").display(),
+      bin.join("composer.phar").display()
+    );
+    crate::disk::atomic_write(bin.join("composer"), wrapper)?;
+    #[cfg(unix)]
+    {
+      use std::os::unix::fs::PermissionsExt;
+      std::fs::set_permissions(bin.join("composer"), std::fs::Permissions::from_mode(0o755))?;
+    }
+
+    spinner.finish_and_clear();
+    println!("✅ {} installed for PHP {}", composer_version(&source)?, source.name());
+    Ok(())
+  }
+}
+
+/// Path of a release under the download URL, channels are published as `latest-<channel>`
+fn release_path(version: &str) -> String {
+  match version {
+    "stable" | "preview" | "snapshot" => format!("latest-{version}"),
+    version if version.ends_with(".x") => format!("latest-{version}"),
+    version => version.to_owned(),
+  }
+}
+
+fn has_composer(source: &SourcePHP) -> bool {
+  source.dist().join("bin/composer.phar").is_file()
+}
+
+fn self_update(source: &SourcePHP) -> Maybe<()> {
+  ensure!(
+    has_composer(source),
+    "Composer isn't installed, run `maphp composer install --php {}`",
+    source.name()
+  );
+
+  let status = std::process::Command::new(source.dist().join("bin/composer"))
+    .arg("self-update")
+    .arg("--no-interaction")
+    .stdin(Stdio::null())
+    .status()?;
+  ensure!(status.success(), "composer self-update exited with {status}");
+
+  println!("✅ PHP {}: {}", source.name(), composer_version(source)?);
+  Ok(())
+}
+
+/// `Composer version 2.8.1 2024-10-04 ...` without the date
+fn composer_version(source: &SourcePHP) -> Maybe<String> {
+  let output = std::process::Command::new(source.dist().join("bin/composer"))
+    .arg("--version")
+    .arg("--no-ansi")
+    .stdin(Stdio::null())
+    .output()?;
+  ensure!(
+    output.status.success(),
+    "composer --version failed: {}",
+    String::from_utf8_lossy(&output.stderr).trim()
+  );
+
+  let version = String::from_utf8_lossy(&output.stdout);
+  let version = version.lines().find(|line| line.starts_with("Composer")).unwrap_or_default();
+  Ok(version.split_whitespace().take(3).collect::<Vec<_>>().join(" "))
+}
@@ -0,0 +1,31 @@
+use crate::CLI;
+use crate::Maybe;
+use crate::config::Config;
+use crate::output::{Format, FormatArgs, print_json, print_version};
+use crate::stats::releases::ReleaseIndex;
+use clap::Args;
+
+#[derive(Args, Clone, Debug)]
+pub struct CurrentArgs {
+  #[command(flatten)]
+  format: FormatArgs,
+}
+
+impl CurrentArgs {
+  pub async fn handle(&self) -> Maybe<()> {
+    let active = CLI.manager().active()?;
+
+    match (active, self.format.format()) {
+      (Some(source), Format::Plain) => println!("{}", source.name()),
+      (Some(source), format) => print_version(&source.info().with_release(ReleaseIndex::load(CLI.releases_file()).as_ref()), format)?,
+      (None, Format::Json) => print_json(&None::<()>)?,
+      (None, Format::Plain) => {}
+      (None, Format::Table) => match Config::load(CLI.config_file())?.default {
+        Some(default) => println!("No active version, run `maphp use {default}` to activate the default"),
+        None => println!("No active version"),
+      },
+    }
+
+    Ok(())
+  }
+}
@@ -0,0 +1,52 @@
+use crate::CLI;
+use crate::Maybe;
+use crate::config::Config;
+use crate::source::SourcePHP;
+use anyhow::ensure;
+use clap::Args;
+
+#[derive(Args, Clone, Debug)]
+pub struct DefaultArgs {
+  /// Installed version or alias, shows the current default when omitted
+  #[arg(default_value = None, conflicts_with = "unset")]
+  version: Option<String>,
+
+  /// Clear the default version
+  #[arg(long, default_value_t = false)]
+  unset: bool,
+}
+
+impl DefaultArgs {
+  pub async fn handle(&self) -> Maybe<()> {
+    let mut config = Config::load(CLI.config_file())?;
+
+    if self.unset {
+      config.default = None;
+      config.save(CLI.config_file())?;
+      println!("Default version cleared");
+      return Ok(());
+    }
+
+    let Some(ref version) = self.version else {
+      match config.default {
+        Some(default) => println!("{default}"),
+        None => println!("No default version, set one with `maphp default <version>`"),
+      }
+      return Ok(());
+    };
+
+    let source = SourcePHP::new(CLI.resolve(version)?);
+    ensure!(source.is_installed(), "Version `{version}` is not installed");
+
+    config.default = Some(source.name().into_owned());
+    config.save(CLI.config_file())?;
+    println!("Default version is now {}", source.name());
+
+    if !CLI.bin().exists() {
+      CLI.manager().link(&source).await?;
+      println!("{} is now active", source.name());
+    }
+
+    Ok(())
+  }
+}
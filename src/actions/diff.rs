@@ -0,0 +1,210 @@
+use crate::CLI;
+use crate::Maybe;
+use crate::output::{Format, FormatArgs, print_json};
+use crate::source::SourcePHP;
+use anyhow::ensure;
+use clap::Args;
+use colored::Colorize;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::process::Stdio;
+
+/// Prints every ini directive with its effective value, one `key=value` per line
+const INI_DUMP: &str = r#"foreach (ini_get_all(null, false) as $k => $v) echo $k, "=", str_replace("\n", "\\n", (string) $v), "\n";"#;
+
+#[derive(Args, Clone, Debug)]
+pub struct DiffArgs {
+  /// Installed version or alias
+  a: String,
+
+  /// Installed version or alias to compare with
+  b: String,
+
+  #[command(flatten)]
+  format: FormatArgs,
+}
+
+/// What a version is made of, paths inside its own source tree are replaced by `<src>`
+#[derive(Debug, Default)]
+struct Snapshot {
+  extensions: BTreeMap<String, String>,
+  configure: BTreeMap<String, String>,
+  ini: BTreeMap<String, String>,
+  manifest: BTreeMap<String, String>,
+}
+
+/// A setting that differs, `None` when the version doesn't have it
+#[derive(Debug, PartialEq, Serialize)]
+struct Difference {
+  section: &'static str,
+  key: String,
+  a: Option<String>,
+  b: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct Report {
+  a: String,
+  b: String,
+  differences: Vec<Difference>,
+}
+
+impl DiffArgs {
+  pub async fn handle(&self) -> Maybe<()> {
+    let a = CLI.target(Some(&self.a))?;
+    let b = CLI.target(Some(&self.b))?;
+    let (snapshot_a, snapshot_b) = (snapshot(&a)?, snapshot(&b)?);
+
+    let mut differences = vec![];
+    differences.extend(diff("extension", &snapshot_a.extensions, &snapshot_b.extensions));
+    differences.extend(diff("configure", &snapshot_a.configure, &snapshot_b.configure));
+    differences.extend(diff("ini", &snapshot_a.ini, &snapshot_b.ini));
+    differences.extend(diff("manifest", &snapshot_a.manifest, &snapshot_b.manifest));
+
+    let report = Report {
+      a: a.name().into_owned(),
+      b: b.name().into_owned(),
+      differences,
+    };
+    match self.format.format() {
+      Format::Json => print_json(&report)?,
+      Format::Plain => report.differences.iter().for_each(|d| {
+        println!(
+          "{}\t{}\t{}\t{}",
+          d.section,
+          d.key,
+          d.a.as_deref().unwrap_or_default(),
+          d.b.as_deref().unwrap_or_default()
+        )
+      }),
+      Format::Table => print_report(&report),
+    }
+
+    Ok(())
+  }
+}
+
+fn php(source: &SourcePHP, args: &[&str]) -> Maybe<String> {
+  let output = std::process::Command::new(source.dist().join("bin/php"))
+    .args(args)
+    .stdin(Stdio::null())
+    .output()?;
+  ensure!(
+    output.status.success(),
+    "php {} of {} failed: {}",
+    args.join(" "),
+    source.name(),
+    String::from_utf8_lossy(&output.stderr).trim()
+  );
+
+  // Version specific paths would make every path setting differ
+  let src = source.src().display().to_string();
+  Ok(String::from_utf8_lossy(&output.stdout).replace(&src, "<src>"))
+}
+
+fn snapshot(source: &SourcePHP) -> Maybe<Snapshot> {
+  let mut snapshot = Snapshot::default();
+
+  let modules = php(source, &["-m"])?;
+  snapshot.extensions = modules
+    .lines()
+    .map(str::trim)
+    .filter(|line| !line.is_empty() && !line.starts_with('['))
+    .map(|module| (module.to_owned(), "loaded".to_owned()))
+    .collect();
+
+  let info = php(source, &["-i"])?;
+  let configure = info
+    .lines()
+    .find_map(|line| line.strip_prefix("Configure Command =>"))
+    .unwrap_or_default();
+  snapshot.configure = configure
+    .split_whitespace()
+    .map(|arg| arg.trim_matches('\''))
+    .filter(|arg| arg.starts_with("--"))
+    .map(|arg| match arg.split_once('=') {
+      Some((key, value)) => (key.to_owned(), value.to_owned()),
+      None => (arg.to_owned(), "yes".to_owned()),
+    })
+    .collect();
+
+  let ini = php(source, &["-r", INI_DUMP])?;
+  snapshot.ini = ini
+    .lines()
+    .filter_map(|line| line.split_once('='))
+    .map(|(key, value)| (key.to_owned(), value.to_owned()))
+    .collect();
+
+  if let Some(options) = source.build_options() {
+    snapshot.manifest.insert("debug".to_owned(), options.debug.to_string());
+    snapshot.manifest.insert("configure_args".to_owned(), options.configure_args.join(" "));
+  }
+  if let Some(variant) = source.variant() {
+    snapshot.manifest.insert("variant".to_owned(), variant);
+  }
+
+  Ok(snapshot)
+}
+
+/// Keys whose value differs or that only one side has, in key order
+fn diff(section: &'static str, a: &BTreeMap<String, String>, b: &BTreeMap<String, String>) -> Vec<Difference> {
+  let keys = a.keys().chain(b.keys()).collect::<BTreeSet<_>>();
+  keys
+    .into_iter()
+    .filter(|key| a.get(*key) != b.get(*key))
+    .map(|key| Difference {
+      section,
+      key: key.clone(),
+      a: a.get(key).cloned(),
+      b: b.get(key).cloned(),
+    })
+    .collect()
+}
+
+/// Aligned table, removals in red, additions in green and changes in yellow
+fn print_report(report: &Report) {
+  if report.differences.is_empty() {
+    println!("No differences between {} and {}", report.a, report.b);
+    return;
+  }
+
+  let cell = |value: &Option<String>| value.clone().unwrap_or_else(|| "-".to_owned());
+  let width = |header: &str, column: &dyn Fn(&Difference) -> usize| {
+    report
+      .differences
+      .iter()
+      .map(column)
+      .max()
+      .unwrap_or_default()
+      .max(header.chars().count())
+  };
+  let section = width("SECTION", &|d| d.section.len());
+  let key = width("KEY", &|d| d.key.chars().count());
+  let a = width(&report.a, &|d| cell(&d.a).chars().count()).min(40);
+
+  println!("{:<section$}  {:<key$}  {:<a$}  {}", "SECTION", "KEY", report.a, report.b);
+  for d in &report.differences {
+    let line = format!("{:<section$}  {:<key$}  {:<a$}  {}", d.section, d.key, cell(&d.a), cell(&d.b));
+    match (&d.a, &d.b) {
+      (Some(_), None) => println!("{}", line.red()),
+      (None, Some(_)) => println!("{}", line.green()),
+      _ => println!("{}", line.yellow()),
+    }
+  }
+}
+
+#[test]
+fn test_diff() {
+  let a = BTreeMap::from([("memory_limit", "128M"), ("mbstring", "loaded"), ("same", "1")].map(|(k, v)| (k.to_owned(), v.to_owned())));
+  let b = BTreeMap::from([("memory_limit", "256M"), ("same", "1"), ("zip", "loaded")].map(|(k, v)| (k.to_owned(), v.to_owned())));
+
+  let keys = diff("ini", &a, &b).into_iter().map(|d| (d.key, d.a, d.b)).collect::<Vec<_>>();
+  assert_eq!(
+    keys,
+    [
+      ("mbstring".to_owned(), Some("loaded".to_owned()), None),
+      ("memory_limit".to_owned(), Some("128M".to_owned()), Some("256M".to_owned())),
+      ("zip".to_owned(), None, Some("loaded".to_owned())),
+    ]
+  );
+}
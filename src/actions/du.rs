@@ -0,0 +1,126 @@
+use crate::CLI;
+use crate::Maybe;
+use crate::disk::{Usage, remove_build_artifacts};
+use crate::output::{Format, FormatArgs, print_json, print_table};
+use crate::source::SourcePHP;
+use clap::Args;
+use colored::Colorize;
+use indicatif::HumanBytes;
+use serde::Serialize;
+
+#[derive(Args, Clone, Debug)]
+pub struct DuArgs {
+  /// Delete object files of every build (`make clean` equivalent), installed versions keep working
+  #[arg(long, default_value_t = false)]
+  prune_build_artifacts: bool,
+
+  #[command(flatten)]
+  format: FormatArgs,
+}
+
+/// Disk usage of one `archives/<version>` directory
+#[derive(Debug, Serialize)]
+struct VersionUsage {
+  version: String,
+  installed: bool,
+  #[serde(flatten)]
+  usage: Usage,
+  total: u64,
+}
+
+#[derive(Debug, Serialize)]
+struct Report {
+  versions: Vec<VersionUsage>,
+  /// Sum of every version
+  total: Usage,
+  /// Tag cache, release index and other files at the root of the work dir
+  metadata: u64,
+}
+
+impl DuArgs {
+  pub async fn handle(&self) -> Maybe<()> {
+    let mut sources = std::fs::read_dir(CLI.archives())?
+      .flatten()
+      .filter(|entry| entry.path().is_dir())
+      .map(|entry| SourcePHP::new(entry.path()))
+      .collect::<Vec<_>>();
+    sources.sort_by_key(|source| std::cmp::Reverse(source.version()));
+
+    if self.prune_build_artifacts {
+      let mut freed = 0;
+      for source in &sources {
+        let src = source.src().to_path_buf();
+        freed += tokio::task::spawn_blocking(move || remove_build_artifacts(&src)).await??;
+      }
+      eprintln!("Removed build artifacts, {} freed", HumanBytes(freed));
+    }
+
+    let mut total = Usage::default();
+    let versions = sources
+      .iter()
+      .map(|source| {
+        let usage = Usage::of(source.src());
+        total.add(&usage);
+        VersionUsage {
+          version: source.name().into_owned(),
+          installed: source.is_installed(),
+          usage,
+          total: usage.total(),
+        }
+      })
+      .collect::<Vec<_>>();
+
+    let metadata = std::fs::read_dir(CLI.work_dir())?
+      .flatten()
+      .filter(|entry| entry.file_type().is_ok_and(|kind| kind.is_file()))
+      .map(|entry| entry.metadata().map(|m| m.len()).unwrap_or_default())
+      .sum::<u64>();
+    let report = Report { versions, total, metadata };
+
+    match self.format.format() {
+      Format::Json => print_json(&report)?,
+      Format::Plain => report.versions.iter().for_each(|v| println!("{} {}", v.version, v.total)),
+      Format::Table => print_report(&report),
+    }
+
+    Ok(())
+  }
+}
+
+fn print_report(report: &Report) {
+  let size = |bytes: u64| HumanBytes(bytes).to_string();
+  let row = |version: String, usage: &Usage| {
+    vec![
+      version,
+      size(usage.source),
+      size(usage.build),
+      size(usage.dist),
+      size(usage.logs),
+      size(usage.tarballs),
+      size(usage.total()),
+    ]
+  };
+
+  let mut rows = report
+    .versions
+    .iter()
+    .map(|v| match v.installed {
+      true => row(v.version.clone(), &v.usage),
+      false => row(format!("{} (partial)", v.version), &v.usage),
+    })
+    .collect::<Vec<_>>();
+  rows.push(row("total".to_owned(), &report.total));
+
+  print_table(&["VERSION", "SOURCE", "BUILD", "DIST", "LOGS", "TARBALLS", "TOTAL"], &rows);
+  println!();
+  println!("Metadata: {} ({})", size(report.metadata), CLI.work_dir().display());
+  if report.total.build > 0 {
+    println!(
+      "{}",
+      format!("Run `maphp du --prune-build-artifacts` to free {}", size(report.total.build)).dimmed()
+    );
+  }
+  if report.versions.iter().any(|v| !v.installed) {
+    println!("{}", "Run `maphp prune` to remove partial sources".dimmed());
+  }
+}
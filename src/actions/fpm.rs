@@ -0,0 +1,82 @@
+use crate::CLI;
+use crate::Maybe;
+use crate::fpm::{Fpm, available};
+use crate::output::print_table;
+use crate::source::SourcePHP;
+use anyhow::ensure;
+use clap::{Args, Subcommand};
+
+#[derive(Args, Clone, Debug)]
+pub struct FpmArgs {
+  #[command(subcommand)]
+  command: FpmCommand,
+}
+
+#[derive(Subcommand, Clone, Debug)]
+enum FpmCommand {
+  /// Start php-fpm of a version, defaults to the active one
+  Start { version: Option<String> },
+  /// Stop php-fpm gracefully
+  Stop { version: Option<String> },
+  /// Stop then start php-fpm, picking up php.ini and pool changes
+  Restart { version: Option<String> },
+  /// Show whether php-fpm runs, every version built with FPM when none is given
+  Status { version: Option<String> },
+}
+
+impl FpmArgs {
+  pub async fn handle(&self) -> Maybe<()> {
+    match self.command {
+      FpmCommand::Start { ref version } => start(&CLI.target(version.as_deref())?).await,
+      FpmCommand::Stop { ref version } => stop(&CLI.target(version.as_deref())?).await,
+      FpmCommand::Restart { ref version } => {
+        let source = CLI.target(version.as_deref())?;
+        stop(&source).await?;
+        start(&source).await
+      }
+      FpmCommand::Status { ref version } => {
+        let sources = match version {
+          Some(version) => vec![CLI.target(Some(version))?],
+          None => available(CLI.archives())?,
+        };
+        ensure!(!sources.is_empty(), "No installed version was built with --enable-fpm");
+
+        let rows = sources
+          .iter()
+          .map(|source| {
+            let fpm = Fpm::new(source);
+            let status = match fpm.pid() {
+              Some(pid) => format!("running (pid {pid})"),
+              None => "stopped".to_owned(),
+            };
+            vec![source.name().into_owned(), status, fpm.socket().display().to_string()]
+          })
+          .collect::<Vec<_>>();
+        print_table(&["VERSION", "STATUS", "LISTEN"], &rows);
+        Ok(())
+      }
+    }
+  }
+}
+
+async fn start(source: &SourcePHP) -> Maybe<()> {
+  let fpm = Fpm::new(source);
+  let src = source.src().to_path_buf();
+  let pid = tokio::task::spawn_blocking(move || Fpm::new(&SourcePHP::new(src)).start()).await??;
+  println!(
+    "✅ php-fpm {} started (pid {pid}), listening on {}",
+    source.name(),
+    fpm.socket().display()
+  );
+  println!("Logs: {}", fpm.log_dir().display());
+  Ok(())
+}
+
+async fn stop(source: &SourcePHP) -> Maybe<()> {
+  let src = source.src().to_path_buf();
+  match tokio::task::spawn_blocking(move || Fpm::new(&SourcePHP::new(src)).stop()).await?? {
+    true => println!("php-fpm {} stopped", source.name()),
+    false => println!("php-fpm {} is not running", source.name()),
+  }
+  Ok(())
+}
@@ -0,0 +1,25 @@
+use crate::CLI;
+use crate::Maybe;
+use crate::output::{FormatArgs, print_version};
+use crate::stats::releases::ReleaseIndex;
+use clap::Args;
+
+#[derive(Args, Clone, Debug)]
+pub struct InfoArgs {
+  /// Installed version, defaults to the active one
+  #[arg(default_value = None)]
+  tag: Option<String>,
+
+  #[command(flatten)]
+  format: FormatArgs,
+}
+
+impl InfoArgs {
+  pub async fn handle(&self) -> Maybe<()> {
+    let source = CLI.target(self.tag.as_deref())?;
+    print_version(
+      &source.info().with_release(ReleaseIndex::load(CLI.releases_file()).as_ref()),
+      self.format.format(),
+    )
+  }
+}
@@ -1,13 +1,17 @@
+use crate::CLI;
 use crate::Maybe;
-use crate::source::SourcePHP;
-use crate::static_const::{CLI, THEME};
-use crate::stats::Tag;
-use anyhow::bail;
+use crate::config::Config;
+use crate::output::{Format, FormatArgs, print_versions};
+use crate::progress::Reporter;
+use crate::source::{SourcePHP, VersionInfo};
+use crate::stats::cache::{SCHEMA, TagCache};
+use crate::stats::releases::{ReleaseIndex, Support};
+use crate::stats::{Tag, TagPage, Version, fetch_tags};
+use anyhow::Context;
 use clap::Args;
+use colored::Colorize;
 use std::collections::HashSet;
-use std::io::Write;
 use std::num::NonZeroU64;
-use std::time::Duration;
 
 #[derive(Args, Clone, Debug)]
 pub struct ListArgs {
@@ -15,7 +19,7 @@ pub struct ListArgs {
   #[arg(long, default_value_t = false)]
   only_installed: bool,
 
-  /// List all versions including from repo
+  /// Include ALPHA, BETA and RC versions
   #[arg(long, default_value_t = false, conflicts_with_all = ["rc", "beta", "alpha"])]
   all: bool,
 
@@ -31,9 +35,16 @@ pub struct ListArgs {
   #[arg(long, default_value_t = false)]
   rc: bool,
 
-  /// Fetch and update known tags
+  /// Show every patch release instead of only the latest of each series
+  #[arg(long, default_value_t = false)]
+  all_patches: bool,
+
+  /// Fetch and update known tags now, regardless of `--cache-ttl`
   #[arg(long, default_value_t = false)]
   fetch: bool,
+
+  #[command(flatten)]
+  format: FormatArgs,
 }
 
 impl ListArgs {
@@ -42,91 +53,217 @@ impl ListArgs {
       self.show_local()
     } else if self.fetch {
       self.fetch().await
-    } else if let Some(tags) = find_local_tags() {
-      let filtered_tags = tags.into_iter().filter(|tag| self.filter_criteria(tag)).collect::<Vec<_>>();
-      if filtered_tags.len() <= 20 {
-        println!("Available versions");
-        for tag in filtered_tags {
-          println!("    - {}", tag.name);
+    } else {
+      let tags = find_local_tags().await?;
+      let locals = SourcePHP::scan_local(CLI.archives())?;
+      let index = ReleaseIndex::load(CLI.releases_file());
+      let mut versions = tags
+        .into_iter()
+        .filter(|tag| self.filter_criteria(tag))
+        .filter_map(|tag| tag.version())
+        .collect::<Vec<_>>();
+      versions.sort_unstable_by(|a, b| b.cmp(a));
+      versions.dedup();
+
+      if !self.all_patches {
+        versions = latest_per_series(versions, &locals);
+      }
+
+      let versions = versions
+        .into_iter()
+        .map(|version| {
+          let name = version.to_string();
+          match locals.iter().find(|local| local.name() == name) {
+            Some(local) => local.info(),
+            None => VersionInfo::remote(name),
+          }
+          .with_release(index.as_ref())
+        })
+        .collect::<Vec<_>>();
+
+      match self.format.format() {
+        Format::Table => {
+          print_grouped(&versions);
+          print_resolution();
         }
-      } else {
-        dialoguer::FuzzySelect::with_theme(&*THEME)
-          .with_prompt("Available versions")
-          .items(filtered_tags.iter().map(Tag::as_semver))
-          .max_length(20)
-          .interact()
-          .ok();
+        format => print_versions(&versions, format)?,
       }
 
       Ok(())
-    } else {
-      bail!("Couldn't find any matching version")
     }
   }
 
   fn filter_criteria(&self, tag: &Tag) -> bool {
-    self.all || self.alpha && tag.is_alpha() || self.beta && tag.is_beta() || self.rc && tag.is_rc()
+    tag.is_stable() || self.all || self.alpha && tag.is_alpha() || self.beta && tag.is_beta() || self.rc && tag.is_rc()
   }
 
   async fn fetch(&self) -> Maybe<()> {
-    let mut local = find_local_tags().unwrap_or_default();
-    let spinner = indicatif::ProgressBar::new_spinner();
-    spinner.enable_steady_tick(Duration::from_secs_f32(crate::static_const::FPS));
-
-    let mut total_new_tag = 0;
-    let mut page = NonZeroU64::new(1).unwrap();
-    spinner.set_message(format!("Fetching page {}...", page));
-    'fetch: while let Some(git_tags) = crate::stats::get_tags(page).await? {
-      page = page.checked_add(1).unwrap();
-
-      for tag in git_tags {
-        if !local.insert(tag) {
-          break 'fetch;
-        }
+    refresh(load_cache().unwrap_or_default()).await?;
+    Ok(())
+  }
 
-        total_new_tag += 1;
-      }
+  fn show_local(&self) -> Maybe<()> {
+    let mut locals = SourcePHP::scan_local(CLI.archives())?;
+    locals.sort_by_key(|local| std::cmp::Reverse(local.version()));
+    let index = ReleaseIndex::load(CLI.releases_file());
+    let locals = locals.iter().map(|local| local.info().with_release(index.as_ref())).collect::<Vec<_>>();
+
+    print_versions(&locals, self.format.format())
+  }
+}
 
-      spinner.set_message(format!("Fetching page {}...", page));
+/// Local tag cache, an unreadable cache is reported and treated as missing
+fn load_cache() -> Option<TagCache> {
+  match TagCache::load(CLI.tags_file()) {
+    Ok(cache) => cache,
+    Err(err) => {
+      eprintln!("⚠️ Ignoring tag cache: {err:#}");
+      None
     }
+  }
+}
+
+/// Known tags, refreshed first when the cache is missing or older than `--cache-ttl`.
+///
+/// A stale cache is still used when the refresh fails.
+pub(crate) async fn find_local_tags() -> Maybe<HashSet<Tag>> {
+  match load_cache() {
+    Some(cache) if !cache.is_stale(CLI.cache_ttl()) => Ok(cache.tags),
+    Some(cache) => match refresh(cache.clone()).await {
+      Ok(cache) => Ok(cache.tags),
+      Err(err) => {
+        eprintln!("⚠️ Couldn't refresh tags, using the cached ones: {err:#}");
+        Ok(cache.tags)
+      }
+    },
+    None => Ok(refresh(TagCache::default()).await?.tags),
+  }
+}
 
-    spinner.println(format!("Found {total_new_tag} tags, updating local file.."));
+/// Walk every tags page and merge them into `cache`, unchanged pages are free thanks to their `ETag`.
+///
+/// An interrupted refresh (e.g. rate limit) still saves what was fetched but keeps the previous
+/// `fetched_at`, so the next run tries again.
+async fn refresh(mut cache: TagCache) -> Maybe<TagCache> {
+  let spinner = Reporter::spinner(CLI.manager().options().progress);
 
-    let json = serde_json::to_string(&local)?;
-    let mut file = std::fs::File::options().truncate(true).write(true).create(true).open(CLI.tags_file())?;
+  let client = crate::github::client()?;
+  let mut total_new_tag = 0;
+  let mut complete = true;
+  let mut page = NonZeroU64::MIN;
 
-    match file.write(json.as_bytes()) {
-      Ok(size) => {
-        let message = format!(
-          "New tag added: {total_new_tag}\
-          \n  Written {size} bytes to local file"
-        );
-        spinner.finish_with_message(message);
-        Ok(())
+  loop {
+    spinner.set_prefix(format!("Fetching tags page {page}..."));
+    let etag = cache.etags.get(&page.get()).filter(|_| !cache.tags.is_empty()).cloned();
+
+    match fetch_tags(&client, page, etag.as_deref()).await {
+      Ok(TagPage::End) => break,
+      Ok(TagPage::NotModified) => {}
+      Ok(TagPage::Tags { tags, etag }) => {
+        match etag {
+          Some(etag) => cache.etags.insert(page.get(), etag),
+          None => cache.etags.remove(&page.get()),
+        };
+
+        for tag in tags {
+          total_new_tag += usize::from(cache.tags.insert(tag));
+        }
       }
-      Err(err) => bail!(format!("Couldn't save fetched tags to local files: {err}")),
+      Err(err) if page.get() > 1 => {
+        spinner.println(format!("⚠️ Stopped at page {page}: {err:#}"));
+        complete = false;
+        break;
+      }
+      Err(err) => return Err(err),
     }
+
+    page = page.saturating_add(1);
   }
 
-  fn show_local(&self) -> Maybe<()> {
-    let locals = SourcePHP::scan_local()?.iter().map(SourcePHP::details).collect::<HashSet<_>>();
+  if complete {
+    cache.etags.retain(|known, _| *known < page.get());
+    cache.fetched_at = crate::date::unix_now();
+  }
+  cache.schema = SCHEMA;
+  cache.save(CLI.tags_file()).context("Couldn't save fetched tags to local file")?;
 
-    println!("All installed version:");
-    for local in locals {
-      println!("  - {local}");
-    }
-    Ok(())
+  spinner.set_prefix("Fetching release index...");
+  if let Err(err) = ReleaseIndex::fetch(CLI.index_url())
+    .await
+    .and_then(|index| index.save(CLI.releases_file()))
+  {
+    spinner.println(format!("⚠️ Couldn't update release index: {err:#}"));
+  }
+
+  spinner.finish_with_message(format!("New tag added: {total_new_tag}, {} known", cache.tags.len()));
+  Ok(cache)
+}
+
+/// Keep the newest version of each minor series plus every installed one, `versions` must be sorted descending.
+fn latest_per_series(versions: Vec<Version>, locals: &[SourcePHP]) -> Vec<Version> {
+  let installed = locals.iter().filter_map(SourcePHP::version).collect::<HashSet<_>>();
+  let mut last_series = None;
+
+  versions
+    .into_iter()
+    .filter(|version| {
+      let newest = last_series != Some(version.series());
+      last_series = Some(version.series());
+      newest || installed.contains(version)
+    })
+    .collect()
+}
+
+/// Print which version `php` resolves to and the configured default
+fn print_resolution() {
+  let active = CLI.manager().active().ok().flatten();
+  let active = active.as_ref().map(SourcePHP::name);
+  let default = Config::load(CLI.config_file()).ok().and_then(|config| config.default);
+
+  println!();
+  match active {
+    Some(ref active) => println!("Active:  {}", active.green().bold()),
+    None => println!("Active:  {}", "none".dimmed()),
+  }
+  match default {
+    Some(ref default) if active.as_deref() != Some(default) => println!("Default: {default} {}", "(overridden by `maphp use`)".dimmed()),
+    Some(default) => println!("Default: {default}"),
+    None => println!("Default: {}", "none".dimmed()),
   }
 }
 
-fn find_local_tags() -> Option<HashSet<Tag>> {
-  let local_tags = CLI.tags_file();
+/// Print versions grouped by minor series with installed and active markers
+fn print_grouped(versions: &[VersionInfo]) {
+  let mut last_series = None;
+
+  for info in versions {
+    let Ok(version) = info.version.parse::<Version>() else { continue };
+    if last_series != Some(version.series()) {
+      last_series = Some(version.series());
+      let series = format!("PHP {}.{}", version.major, version.minor).bold();
+      match info.support {
+        Some(Support::Eol) => println!("{series} {}", "(end of life)".red()),
+        Some(Support::Security) => println!("{series} {}", "(security fixes only)".yellow()),
+        _ => println!("{series}"),
+      }
+    }
+
+    let line = format!("{:<14}", info.version);
+    let markers = [
+      (info.installed, "installed"),
+      (info.active, "active"),
+      (info.default, "default"),
+      (info.broken, "broken"),
+    ]
+    .into_iter()
+    .filter_map(|(set, marker)| set.then_some(marker))
+    .collect::<Vec<_>>()
+    .join(", ");
 
-  match local_tags.exists() {
-    true => {
-      let reader = std::fs::File::open(local_tags).ok()?;
-      serde_json::from_reader(reader).ok()
+    match (info.active, info.installed) {
+      (true, _) => println!("  {} {} {}", "*".green().bold(), line.green().bold(), format!("({markers})").dimmed()),
+      (false, true) => println!("    {} {}", line.green(), format!("({markers})").dimmed()),
+      (false, false) => println!("    {}", info.version),
     }
-    false => None,
   }
 }
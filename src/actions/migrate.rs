@@ -0,0 +1,181 @@
+use crate::CLI;
+use crate::Maybe;
+use crate::disk::copy_tree;
+use crate::fpm::Fpm;
+use crate::package::{configure_relocated_ini, relocate};
+use crate::source::SourcePHP;
+use crate::{managed_dir, parse_work_dir};
+use anyhow::{Context, bail, ensure};
+use clap::Args;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+
+#[derive(Args, Clone, Debug)]
+pub struct MigrateArgs {
+  /// New work dir, `.maphp` is appended unless it already ends with it (same as `--work-dir`)
+  #[arg(long, value_name = "DIR", value_parser = parse_work_dir)]
+  to: PathBuf,
+}
+
+impl MigrateArgs {
+  pub async fn handle(&self) -> Maybe<()> {
+    let from = CLI.work_dir().to_path_buf();
+    let to = std::path::absolute(managed_dir(&self.to))?;
+    ensure!(
+      !to.starts_with(&from) && !from.starts_with(&to),
+      "{} and {} overlap",
+      from.display(),
+      to.display()
+    );
+    ensure!(
+      !to.exists() || to.read_dir()?.next().is_none(),
+      "{} already exists and isn't empty",
+      to.display()
+    );
+
+    let versions = SourcePHP::scan_local(CLI.archives())?;
+    if let Some(running) = versions.iter().find(|source| Fpm::new(source).pid().is_some()) {
+      bail!("php-fpm {0} is running, stop it first with `maphp fpm stop {0}`", running.name());
+    }
+    let active = versions
+      .iter()
+      .find(|source| source.is_in_path())
+      .map(|source| source.name().into_owned());
+    let names = versions.iter().map(|source| source.name().into_owned()).collect::<Vec<_>>();
+
+    println!("Moving {} to {}", from.display(), to.display());
+    let prompt = format!("Migrate {} installed version(s)?", names.len());
+    match CLI.confirm(prompt, Some(true)) {
+      Ok(Some(true)) => {}
+      Ok(None) => bail!("Refusing to migrate without confirmation, pass --yes"),
+      _ => bail!("Operation canceled"),
+    }
+
+    // Copied into a staging dir renamed to `to` once complete. The old work dir is moved aside while
+    // the relocated versions are checked, so they can't lean on it, and only deleted once they all run.
+    // Any failure before that restores it and removes the copy
+    let hidden = from.with_file_name(format!("{}.old", from.file_name().unwrap_or_default().to_string_lossy()));
+    ensure!(!hidden.exists(), "{} is in the way, move or remove it first", hidden.display());
+    let staging = to.with_file_name(format!("{}.migrating", to.file_name().unwrap_or_default().to_string_lossy()));
+    if staging.exists() {
+      // Left by an interrupted migration
+      std::fs::remove_dir_all(&staging)?;
+    }
+    if to.exists() {
+      std::fs::remove_dir(&to)?;
+    }
+    if let Some(parent) = to.parent() {
+      std::fs::create_dir_all(parent)?;
+    }
+
+    let (copy_from, copy_to) = (from.clone(), staging.clone());
+    let migrated = tokio::task::spawn_blocking(move || copy_tree(&copy_from, &copy_to))
+      .await?
+      .and_then(|_| Ok(std::fs::rename(&staging, &to)?))
+      .and_then(|_| relocate_all(&from, &to, active.as_deref(), &names));
+    let checked = migrated.and_then(|_| Ok(std::fs::rename(&from, &hidden)?)).and_then(|_| {
+      let broken = check_all(&to, &names);
+      if let Err(err) = std::fs::rename(&hidden, &from) {
+        bail!("Couldn't move {} back to {}: {err}", hidden.display(), from.display());
+      }
+      ensure!(broken.is_empty(), "{} don't run after migrating", broken.join(", "));
+      Ok(())
+    });
+    if let Err(err) = checked {
+      _ = std::fs::remove_dir_all(&staging);
+      if from.exists() {
+        _ = std::fs::remove_dir_all(&to);
+      }
+      return Err(err.context(format!("Migration rolled back, {} is unchanged", from.display())));
+    }
+    tokio::fs::remove_dir_all(&from)
+      .await
+      .with_context(|| format!("Migrated to {} but couldn't remove {}", to.display(), from.display()))?;
+
+    println!(
+      "Migrated to {}, pass `--work-dir {}` or set WORK_DIR from now on",
+      to.display(),
+      self.to.display()
+    );
+    println!(r"# Update the PATH in your shell profile:");
+    println!(r#"export PATH="{}:$PATH""#, to.join("bin").display());
+
+    Ok(())
+  }
+}
+
+/// Link the active version and relocate every version copied from `from` into `to`
+fn relocate_all(from: &Path, to: &Path, active: Option<&str>, names: &[String]) -> Maybe<()> {
+  let bin = to.join("bin");
+  if bin.symlink_metadata().is_ok_and(|meta| meta.is_symlink()) {
+    std::fs::remove_file(&bin)?;
+  }
+  #[cfg(unix)]
+  if let Some(active) = active {
+    std::os::unix::fs::symlink(to.join("archives").join(active).join("dist/bin"), &bin)?;
+  }
+
+  for name in names {
+    let source = SourcePHP::new(to.join("archives").join(name));
+    let old_dist = from.join("archives").join(name).join("dist");
+    relocate(&source.dist(), &old_dist, &source.dist())?;
+    retarget_links(&source.dist(), from, to)?;
+    configure_relocated_ini(&source)?;
+  }
+
+  Ok(())
+}
+
+/// Run `php -v` of every version in `to`, returns the ones that don't run
+fn check_all(to: &Path, names: &[String]) -> Vec<String> {
+  let mut broken = vec![];
+  for name in names {
+    match php_version(&SourcePHP::new(to.join("archives").join(name))) {
+      Ok(line) => println!("✅ {name}: {line}"),
+      Err(err) => {
+        println!("❌ {name}: {err:#}");
+        broken.push(name.clone());
+      }
+    }
+  }
+
+  broken
+}
+
+/// Point absolute symlinks under `dir` that lead into `from` at the same place under `to`
+fn retarget_links(dir: &Path, from: &Path, to: &Path) -> Maybe<()> {
+  for entry in std::fs::read_dir(dir)?.flatten() {
+    let kind = entry.file_type()?;
+    if kind.is_dir() {
+      retarget_links(&entry.path(), from, to)?;
+      continue;
+    }
+    if !kind.is_symlink() {
+      continue;
+    }
+
+    let target = std::fs::read_link(entry.path())?;
+    if let Ok(relative) = target.strip_prefix(from) {
+      std::fs::remove_file(entry.path())?;
+      #[cfg(unix)]
+      std::os::unix::fs::symlink(to.join(relative), entry.path())?;
+    }
+  }
+
+  Ok(())
+}
+
+/// First line of `php -v`
+fn php_version(source: &SourcePHP) -> Maybe<String> {
+  let output = std::process::Command::new(source.dist().join("bin/php"))
+    .arg("-v")
+    .stdin(Stdio::null())
+    .output()?;
+  ensure!(
+    output.status.success(),
+    "php -v failed: {}",
+    String::from_utf8_lossy(&output.stderr).trim()
+  );
+
+  Ok(String::from_utf8_lossy(&output.stdout).lines().next().unwrap_or_default().to_owned())
+}
@@ -1 +1,18 @@
+pub mod alias;
+pub mod composer;
+pub mod current;
+pub mod default;
+pub mod diff;
+pub mod du;
+pub mod fpm;
+pub mod info;
 pub mod list;
+pub mod migrate;
+pub mod opcache;
+pub mod pack;
+pub mod prune;
+pub mod remove;
+pub mod serve;
+pub mod test;
+pub mod upgrade;
+pub mod xdebug;
@@ -0,0 +1,114 @@
+use crate::CLI;
+use crate::Maybe;
+use crate::ini::{Snippet, conf_dir, ensure_scanned, eval};
+use crate::output::print_warning;
+use crate::source::SourcePHP;
+use anyhow::ensure;
+use clap::{Args, Subcommand};
+use colored::Colorize;
+
+#[derive(Args, Clone, Debug)]
+pub struct OpcacheArgs {
+  /// Shows the current state when omitted
+  #[command(subcommand)]
+  command: Option<OpcacheCommand>,
+
+  /// Installed PHP version or alias, defaults to the active one
+  #[arg(long, global = true)]
+  php: Option<String>,
+}
+
+#[derive(Subcommand, Clone, Debug)]
+enum OpcacheCommand {
+  /// Enable OPcache, for the CLI too
+  On,
+  /// Disable OPcache
+  Off,
+  /// Set `opcache.jit` (PHP 8+) and enable OPcache
+  Jit {
+    /// `tracing`, `function`, `disable`, `off`, or a CRTO number such as 1255
+    mode: String,
+  },
+  /// Show whether OPcache and the JIT are enabled
+  Status,
+}
+
+impl OpcacheArgs {
+  pub async fn handle(&self) -> Maybe<()> {
+    let source = CLI.target(self.php.as_deref())?;
+    let path = conf_dir(&source).join("opcache.ini");
+    let mut snippet = Snippet::load(&path)?;
+
+    match self.command {
+      None | Some(OpcacheCommand::Status) => return status(&source, &snippet),
+      Some(OpcacheCommand::On) => {
+        ensure_scanned(&source)?;
+        enable(&source, &mut snippet);
+      }
+      Some(OpcacheCommand::Off) => {
+        snippet.set("opcache.enable", "0");
+        snippet.set("opcache.enable_cli", "0");
+      }
+      Some(OpcacheCommand::Jit { ref mode }) => {
+        ensure!(
+          source.version().is_some_and(|version| version.series() >= (8, 0)),
+          "The JIT requires PHP 8.0 or newer"
+        );
+        let valid =
+          ["tracing", "function", "disable", "off", "on"].contains(&mode.as_str()) || (mode.len() == 4 && mode.chars().all(|c| c.is_ascii_digit()));
+        ensure!(
+          valid,
+          "Unknown JIT mode `{mode}`, expected tracing, function, disable, off or a CRTO number"
+        );
+
+        ensure_scanned(&source)?;
+        enable(&source, &mut snippet);
+        snippet.set("opcache.jit", mode);
+        if snippet.get("opcache.jit_buffer_size").is_none() {
+          snippet.set("opcache.jit_buffer_size", "64M");
+        }
+      }
+    }
+
+    snippet.save(&path, "opcache")?;
+    status(&source, &snippet)
+  }
+}
+
+/// OPcache is a shared extension unless the build compiled it in (always the case since PHP 8.5)
+fn enable(source: &SourcePHP, snippet: &mut Snippet) {
+  match source.shared_extensions().iter().any(|ext| ext == "opcache") {
+    true => snippet.set("zend_extension", "opcache"),
+    false => snippet.disable("zend_extension"),
+  }
+  snippet.set("opcache.enable", "1");
+  snippet.set("opcache.enable_cli", "1");
+}
+
+/// `on` only when the snippet enabling OPcache is itself loaded
+fn status(source: &SourcePHP, snippet: &Snippet) -> Maybe<()> {
+  let mut enabled = snippet.get("opcache.enable") == Some("1");
+  if enabled && let Err(err) = ensure_scanned(source) {
+    print_warning(format!("opcache.ini isn't loaded: {err}"));
+    enabled = false;
+  }
+  match (enabled, snippet.get("opcache.jit")) {
+    (true, Some(jit)) => println!("OPcache of PHP {}: {} (jit {jit})", source.name(), "on".green()),
+    (true, None) => println!("OPcache of PHP {}: {}", source.name(), "on".green()),
+    (false, _) => println!("OPcache of PHP {}: {}", source.name(), "off".yellow()),
+  }
+
+  let code = "function_exists('opcache_get_status') && opcache_get_status(false) ? 'on' : 'off'";
+  let loaded = eval(source, code);
+  if loaded
+    .as_deref()
+    .is_some_and(|loaded| (loaded == "on" && !enabled) || (loaded == "off" && enabled))
+  {
+    println!(
+      "{} php reports OPcache {}, check `php --ini` for another ini configuring it",
+      "⚠️".yellow(),
+      loaded.unwrap_or_default()
+    );
+  }
+  Ok(())
+}
@@ -0,0 +1,71 @@
+use crate::CLI;
+use crate::Maybe;
+use crate::package::{libc, pack, target};
+use crate::progress::Reporter;
+use crate::repository::{Entry, Index, profile, sha256};
+use crate::source::SourcePHP;
+use anyhow::ensure;
+use clap::Args;
+use indicatif::HumanBytes;
+use std::path::PathBuf;
+
+#[derive(Args, Clone, Debug)]
+pub struct PackArgs {
+  /// Installed version or alias
+  version: String,
+
+  /// Package file, `.tar.gz` or `.tgz` for gzip and zstd otherwise.
+  /// Defaults to `php-<version>-<target>.tar.zst` in the current directory
+  #[arg(short, long, value_name = "FILE")]
+  output: Option<PathBuf>,
+
+  /// Write the package into a binary repository directory and add it to its `index.json`
+  #[arg(long, value_name = "DIR", conflicts_with = "output")]
+  index: Option<PathBuf>,
+}
+
+impl PackArgs {
+  pub async fn handle(&self) -> Maybe<()> {
+    let source = SourcePHP::new(CLI.resolve(&self.version)?);
+    ensure!(source.is_installed(), "Version `{}` is not installed", source.name());
+
+    let options = source.build_options().unwrap_or_default();
+    let output = match (&self.output, &self.index) {
+      (Some(output), _) => output.clone(),
+      (None, Some(dir)) => {
+        std::fs::create_dir_all(dir)?;
+        let libc = if libc().is_empty() { String::new() } else { format!("-{}", libc()) };
+        dir.join(format!("php-{}-{}{libc}-{}.tar.zst", source.name(), target(), profile(&options)))
+      }
+      (None, None) => PathBuf::from(format!("php-{}-{}.tar.zst", source.name(), target())),
+    };
+
+    let spinner = Reporter::spinner(CLI.manager().options().progress);
+    spinner.set_prefix(format!("Packing {}", source.name()));
+    let (src, path) = (source.src().to_path_buf(), output.clone());
+    tokio::task::spawn_blocking(move || pack(&SourcePHP::new(src), &path)).await??;
+
+    let size = output.metadata().map(|m| m.len()).unwrap_or_default();
+    spinner.finish_with_message(format!("✅ {} ({})", output.display(), HumanBytes(size)));
+
+    let Some(ref dir) = self.index else {
+      println!("Install it with `maphp install --from-package {}`", output.display());
+      return Ok(());
+    };
+
+    let mut index = Index::load(dir.join("index.json"))?;
+    index.upsert(Entry {
+      version: source.name().into_owned(),
+      target: target(),
+      libc: libc().to_owned(),
+      profile: profile(&options).to_owned(),
+      configure_args: Some(options.configure_args),
+      file: output.file_name().unwrap_or_default().to_string_lossy().into_owned(),
+      sha256: sha256(std::fs::File::open(&output)?)?,
+    });
+    index.save(dir.join("index.json"))?;
+    println!("Added to {}", dir.join("index.json").display());
+
+    Ok(())
+  }
+}
@@ -0,0 +1,138 @@
+use crate::CLI;
+use crate::Maybe;
+use crate::alias::Aliases;
+use crate::config::Config;
+use crate::disk::dir_size;
+use crate::source::SourcePHP;
+use anyhow::bail;
+use clap::Args;
+use colored::Colorize;
+use indicatif::HumanBytes;
+use std::path::Path;
+use std::time::Duration;
+
+/// Age after which a `.tmp` file is considered left over
+const TMP_MIN_AGE: Duration = Duration::from_secs(3600);
+
+#[derive(Args, Clone, Debug)]
+pub struct PruneArgs {
+  /// Show what would be removed and the space it frees, without removing anything
+  #[arg(long, default_value_t = false)]
+  dry_run: bool,
+}
+
+impl PruneArgs {
+  pub async fn handle(&self) -> Maybe<()> {
+    let installed = |name: &str| SourcePHP::new(CLI.archives().join(name)).is_installed();
+
+    // Sources of failed or interrupted builds and anything else that isn't an installed version,
+    // except the ones a running maphp is building
+    let mut busy = vec![];
+    let mut paths = vec![];
+    for path in std::fs::read_dir(CLI.archives())?.flatten().map(|entry| entry.path()) {
+      let source = SourcePHP::new(&path);
+      match source.locked_by() {
+        _ if source.is_installed() => {}
+        Some(pid) => busy.push((path, pid)),
+        None => paths.push(path),
+      }
+    }
+    // Left over by an interrupted `atomic_write`, recent ones may still be written
+    paths.extend(
+      std::fs::read_dir(CLI.work_dir())?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "tmp"))
+        .filter(|path| {
+          path
+            .metadata()
+            .and_then(|meta| meta.modified())
+            .is_ok_and(|at| at.elapsed().unwrap_or_default() > TMP_MIN_AGE)
+        }),
+    );
+    paths.sort();
+
+    for (path, pid) in &busy {
+      println!("Skipping {}, it's being built (pid {pid})", display(path));
+    }
+
+    let mut aliases = Aliases::load(CLI.aliases_file())?;
+    let dangling_aliases = aliases
+      .iter()
+      .filter(|(_, version)| !installed(version))
+      .map(|(name, version)| (name.to_owned(), version.to_owned()))
+      .collect::<Vec<_>>();
+
+    let mut config = Config::load(CLI.config_file())?;
+    let dangling_default = config.default.clone().filter(|default| !installed(default));
+
+    // `exists` follows the link, a link to a removed version doesn't
+    let dangling_bin = CLI.bin().symlink_metadata().is_ok_and(|meta| meta.is_symlink()) && !CLI.bin().exists();
+
+    if paths.is_empty() && dangling_aliases.is_empty() && dangling_default.is_none() && !dangling_bin {
+      println!("Nothing to prune");
+      return Ok(());
+    }
+
+    let mut total = 0;
+    for path in &paths {
+      let size = if path.is_dir() {
+        dir_size(path)
+      } else {
+        path.metadata().map(|m| m.len()).unwrap_or_default()
+      };
+      total += size;
+      println!("  {:>10}  {}", HumanBytes(size).to_string(), display(path));
+    }
+    for (name, version) in &dangling_aliases {
+      println!("  {:>10}  alias {name} -> {version} {}", "", "(not installed)".yellow());
+    }
+    if let Some(ref default) = dangling_default {
+      println!("  {:>10}  default {default} {}", "", "(not installed)".yellow());
+    }
+    if dangling_bin {
+      println!("  {:>10}  {} {}", "", display(&CLI.bin()), "(dangling link)".yellow());
+    }
+    println!("  {:>10}  {}", HumanBytes(total).to_string().bold(), "total".bold());
+
+    if self.dry_run {
+      println!("Dry run, nothing was removed");
+      return Ok(());
+    }
+
+    let count = paths.len() + dangling_aliases.len() + usize::from(dangling_default.is_some()) + usize::from(dangling_bin);
+    match CLI.confirm(format!("Remove the {count} item(s) listed above, {}?", HumanBytes(total)), Some(false)) {
+      Ok(Some(true)) => {}
+      Ok(None) => bail!("Refusing to prune without confirmation, pass --yes"),
+      _ => bail!("Operation canceled"),
+    }
+
+    for path in &paths {
+      match path.is_dir() {
+        true => tokio::fs::remove_dir_all(path).await?,
+        false => tokio::fs::remove_file(path).await?,
+      }
+    }
+    for (name, _) in &dangling_aliases {
+      aliases.remove(name);
+    }
+    if !dangling_aliases.is_empty() {
+      aliases.save(CLI.aliases_file())?;
+    }
+    if dangling_default.is_some() {
+      config.default = None;
+      config.save(CLI.config_file())?;
+    }
+    if dangling_bin {
+      tokio::fs::remove_file(CLI.bin()).await?;
+    }
+
+    println!("Pruned, {} freed", HumanBytes(total));
+    Ok(())
+  }
+}
+
+/// Path relative to the work dir
+fn display(path: &Path) -> String {
+  path.strip_prefix(CLI.work_dir()).unwrap_or(path).display().to_string()
+}
@@ -0,0 +1,131 @@
+use crate::CLI;
+use crate::Maybe;
+use crate::disk::dir_size;
+use crate::source::SourcePHP;
+use crate::stats::Version;
+use anyhow::{bail, ensure};
+use clap::Args;
+use colored::Colorize;
+use indicatif::HumanBytes;
+
+#[derive(Args, Clone, Debug)]
+pub struct RemoveArgs {
+  /// Version or alias, partially built sources are accepted too
+  #[arg(default_value = None, conflicts_with_all = ["all_except", "older_than"])]
+  tag: Option<String>,
+
+  /// Remove every installed version except these versions or aliases (comma separated)
+  #[arg(long, value_delimiter = ',', num_args = 1..)]
+  all_except: Vec<String>,
+
+  /// Remove every installed version of a series older than this one, e.g. `8.1`
+  #[arg(long, value_name = "SERIES")]
+  older_than: Option<String>,
+
+  /// Show what would be deleted and the space it frees, without deleting anything
+  #[arg(long, default_value_t = false)]
+  dry_run: bool,
+}
+
+impl RemoveArgs {
+  pub async fn handle(&self) -> Maybe<()> {
+    let bulk = !self.all_except.is_empty() || self.older_than.is_some();
+    let targets = match bulk {
+      true => self.bulk_targets()?,
+      false => vec![self.single_target()?],
+    };
+
+    if targets.is_empty() {
+      println!("Nothing to remove");
+      return Ok(());
+    }
+
+    let mut total = 0;
+    for source in &targets {
+      let size = dir_size(source.src());
+      total += size;
+      let partial = if source.is_installed() { "" } else { " (partial)" };
+      println!("  {:<14} {:>10}{}", source.name(), HumanBytes(size).to_string(), partial.yellow());
+    }
+    println!("  {:<14} {:>10}", "total".bold(), HumanBytes(total).to_string().bold());
+
+    if self.dry_run {
+      println!("Dry run, nothing was deleted");
+      return Ok(());
+    }
+
+    let prompt = match targets.as_slice() {
+      [source] => format!("Are you sure want to remove ({})?", source.details().red()),
+      _ => format!("Are you sure want to remove {} versions?", targets.len().to_string().red()),
+    };
+    match CLI.confirm(prompt, Some(false)) {
+      Ok(Some(true)) => {}
+      Ok(None) => bail!("Refusing to remove without confirmation, pass --yes"),
+      _ => bail!("Operation canceled"),
+    }
+
+    remove_all(&targets).await
+  }
+
+  fn single_target(&self) -> Maybe<SourcePHP> {
+    let src = match self.tag {
+      Some(ref tag) => CLI.resolve(tag)?,
+      None => CLI.select("Choose installed version you want to remove")?,
+    };
+
+    let source = SourcePHP::new(src);
+    ensure!(source.src().is_dir(), "No matching version `{}` as found", source.name());
+    Ok(source)
+  }
+
+  /// Installed versions selected by `--older-than` and `--all-except`
+  fn bulk_targets(&self) -> Maybe<Vec<SourcePHP>> {
+    let older_than = match self.older_than {
+      Some(ref series) => Some(series.parse::<Version>().map_err(anyhow::Error::msg)?.series()),
+      None => None,
+    };
+    let keep = self
+      .all_except
+      .iter()
+      .map(|version| Ok(SourcePHP::new(CLI.resolve(version)?).name().into_owned()))
+      .collect::<Maybe<Vec<_>>>()?;
+
+    let mut targets = SourcePHP::scan_local(CLI.archives())?
+      .into_iter()
+      .filter(|source| !keep.contains(&source.name().into_owned()))
+      .filter(|source| match older_than {
+        Some(series) => source.version().is_some_and(|version| version.series() < series),
+        None => true,
+      })
+      .collect::<Vec<_>>();
+    targets.sort_by_key(|source| source.version());
+
+    Ok(targets)
+  }
+}
+
+/// Remove `targets` and report what happened along with it
+pub(crate) async fn remove_all(targets: &[SourcePHP]) -> Maybe<()> {
+  let removal = CLI.manager().remove(targets).await?;
+
+  for version in &removal.stopped_fpm {
+    println!("php-fpm {version} stopped");
+  }
+  for version in &removal.removed {
+    println!("PHP {version} successfully deleted");
+  }
+  if let Some(ref version) = removal.default_cleared {
+    println!("{version} was the default version, default cleared");
+  }
+  if !removal.aliases_removed.is_empty() {
+    println!("Removed alias(es): {}", removal.aliases_removed.join(", "));
+  }
+  if removal.was_active {
+    match removal.fallback {
+      Some(ref version) => println!("Switched to default version {version}"),
+      None => println!("No default version to fall back to, set one with `maphp default <version>`"),
+    }
+  }
+
+  Ok(())
+}
@@ -0,0 +1,223 @@
+use crate::CLI;
+use crate::Maybe;
+use crate::source::SourcePHP;
+use anyhow::{bail, ensure};
+use clap::Args;
+use colored::Colorize;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::{Duration, SystemTime};
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::{Child, Command};
+
+#[derive(Args, Clone, Debug)]
+pub struct ServeArgs {
+  /// Document root
+  #[arg(default_value = ".")]
+  docroot: PathBuf,
+
+  /// Router script, every request goes through it
+  router: Option<PathBuf>,
+
+  /// PHP version, alias or series such as `8.2`. Defaults to `.php-version` of the docroot or a parent, then the active version
+  #[arg(long)]
+  php: Option<String>,
+
+  #[arg(long, default_value = "127.0.0.1")]
+  host: String,
+
+  #[arg(short, long, default_value_t = 8000)]
+  port: u16,
+
+  /// Load Xdebug with step debugging started on every request
+  #[arg(long, default_value_t = false)]
+  xdebug: bool,
+
+  /// Print the server output as is instead of a condensed access log
+  #[arg(long, default_value_t = false)]
+  raw_log: bool,
+}
+
+impl ServeArgs {
+  pub async fn handle(&self) -> Maybe<()> {
+    ensure!(self.docroot.is_dir(), "Document root {} is not a directory", self.docroot.display());
+    let docroot = std::path::absolute(&self.docroot)?;
+
+    loop {
+      let (source, version_file) = self.select(&docroot)?;
+      let watched = watched_files(&source, version_file.as_deref());
+      let snapshot = mtimes(&watched);
+
+      let mut child = self.spawn(&source, &docroot)?;
+      println!(
+        "{} PHP {} on http://{}:{} serving {}",
+        "▶".green().bold(),
+        source.name(),
+        self.host,
+        self.port,
+        docroot.display()
+      );
+
+      loop {
+        tokio::select! {
+          status = child.wait() => {
+            let status = status?;
+            ensure!(status.success(), "php -S exited with {status}");
+            return Ok(());
+          }
+          _ = tokio::signal::ctrl_c() => {
+            child.kill().await?;
+            return Ok(());
+          }
+          _ = tokio::time::sleep(Duration::from_secs(1)) => {}
+        }
+
+        if mtimes(&watched) != snapshot {
+          println!("{} configuration changed, restarting", "↻".yellow().bold());
+          child.kill().await?;
+          break;
+        }
+      }
+    }
+  }
+
+  /// Version to serve with and the `.php-version` it came from
+  fn select(&self, docroot: &Path) -> Maybe<(SourcePHP, Option<PathBuf>)> {
+    if let Some(ref php) = self.php {
+      return Ok((find_version(php)?, None));
+    }
+
+    let version_file = docroot.ancestors().map(|dir| dir.join(".php-version")).find(|file| file.is_file());
+    if let Some(ref file) = version_file {
+      let spec = std::fs::read_to_string(file)?;
+      return Ok((find_version(spec.trim())?, version_file));
+    }
+
+    match CLI.manager().active()? {
+      Some(source) => Ok((source, None)),
+      None => bail!("No active version and no .php-version found, pass --php <version>"),
+    }
+  }
+
+  fn spawn(&self, source: &SourcePHP, docroot: &Path) -> Maybe<Child> {
+    let mut command = Command::new(source.dist().join("bin/php"));
+    let ini = source.dist().join("lib/php.ini");
+    if ini.is_file() {
+      command.arg("-c").arg(ini);
+    }
+
+    if self.xdebug {
+      let loaded = source.shared_extensions().iter().any(|ext| ext == "xdebug");
+      ensure!(
+        loaded,
+        "Xdebug isn't installed for PHP {0}, run `pecl install xdebug` with PHP {0}",
+        source.name()
+      );
+      // Already loaded when turned on with `maphp xdebug on`, loading it twice warns
+      if crate::ini::eval(source, "extension_loaded('xdebug') ? 1 : 0").as_deref() != Some("1") {
+        command.args(["-d", "zend_extension=xdebug"]);
+      }
+      command.args(["-d", "xdebug.mode=debug", "-d", "xdebug.start_with_request=yes"]);
+    }
+
+    command.arg("-S").arg(format!("{}:{}", self.host, self.port)).arg("-t").arg(docroot);
+    if let Some(ref router) = self.router {
+      command.arg(router);
+    }
+
+    let mut child = command.stdin(Stdio::null()).stderr(Stdio::piped()).kill_on_drop(true).spawn()?;
+    let stderr = child.stderr.take().expect("Unexpected STDIO piped stderr not found");
+    let raw = self.raw_log;
+    tokio::spawn(async move {
+      let mut lines = BufReader::new(stderr).lines();
+      while let Ok(Some(line)) = lines.next_line().await {
+        match raw {
+          true => eprintln!("{line}"),
+          false => {
+            if let Some(line) = format_log(&line) {
+              eprintln!("{line}");
+            }
+          }
+        }
+      }
+    });
+
+    Ok(child)
+  }
+}
+
+/// Installed version, alias, or the newest installed patch of a series such as `8.2`
+fn find_version(spec: &str) -> Maybe<SourcePHP> {
+  let source = SourcePHP::new(CLI.resolve(spec)?);
+  if source.is_installed() {
+    return Ok(source);
+  }
+
+  let prefix = format!("{spec}.");
+  let newest = SourcePHP::scan_local(CLI.archives())?
+    .into_iter()
+    .filter(|source| source.name().starts_with(&prefix))
+    .max_by_key(SourcePHP::version);
+  match newest {
+    Some(source) => Ok(source),
+    None => bail!("PHP `{spec}` is not installed, run `maphp install {spec}`"),
+  }
+}
+
+/// `.php-version` and the ini files of `source`, a restart picks up their changes
+fn watched_files(source: &SourcePHP, version_file: Option<&Path>) -> Vec<PathBuf> {
+  let mut files = vec![source.dist().join("lib/php.ini")];
+  if let Ok(entries) = std::fs::read_dir(source.dist().join("lib/conf.d")) {
+    files.extend(entries.flatten().map(|entry| entry.path()));
+  }
+  files.extend(version_file.map(Path::to_path_buf));
+  files
+}
+
+fn mtimes(files: &[PathBuf]) -> Vec<Option<SystemTime>> {
+  files.iter().map(|file| file.metadata().and_then(|m| m.modified()).ok()).collect()
+}
+
+/// Condense a `php -S` log line to `time status method path`, connection noise is dropped.
+///
+/// `[Sat Oct 18 13:00:01 2026] 127.0.0.1:50000 [200]: GET /index.php`
+fn format_log(line: &str) -> Option<String> {
+  let (time, message) = match line.strip_prefix('[').and_then(|rest| rest.split_once("] ")) {
+    Some((date, message)) => (date.split_whitespace().nth(3).unwrap_or_default(), message),
+    None => return Some(line.to_owned()),
+  };
+
+  let message = match message.split_once(' ') {
+    Some((addr, rest)) if addr.parse::<SocketAddr>().is_ok() => rest,
+    _ => message,
+  };
+  if message == "Accepted" || message == "Closing" {
+    return None;
+  }
+
+  let Some((status, request)) = message.strip_prefix('[').and_then(|rest| rest.split_once("]: ")) else {
+    return Some(format!("{} {message}", time.dimmed()));
+  };
+  let status = match status.chars().next() {
+    Some('2') => status.green(),
+    Some('3') => status.cyan(),
+    Some('4') => status.yellow(),
+    _ => status.red(),
+  };
+
+  Some(format!("{} {status} {request}", time.dimmed()))
+}
+
+#[test]
+fn test_format_log() {
+  assert_eq!(format_log("[Sat Oct 18 13:00:01 2026] 127.0.0.1:50000 Accepted"), None);
+  assert_eq!(format_log("[Sat Oct 18 13:00:01 2026] 127.0.0.1:50000 Closing"), None);
+
+  let line = format_log("[Sat Oct 18 13:00:01 2026] 127.0.0.1:50000 [404]: GET /missing - No such file or directory").unwrap();
+  assert!(line.contains("13:00:01") && line.contains("404") && line.contains("GET /missing"));
+  assert!(!line.contains("127.0.0.1"));
+
+  let line = format_log("[Sat Oct 18 13:00:00 2026] PHP 8.3.0 Development Server (http://127.0.0.1:8000) started").unwrap();
+  assert!(line.contains("PHP 8.3.0 Development Server"));
+}
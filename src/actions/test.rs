@@ -0,0 +1,166 @@
+use crate::CLI;
+use crate::Maybe;
+use crate::output::print_table;
+use crate::source::SourcePHP;
+use anyhow::{bail, ensure};
+use clap::Args;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+
+#[derive(Args, Clone, Debug)]
+pub struct TestArgs {
+  /// Installed version or alias, its source tree must still be there
+  version: String,
+
+  /// Test directory or file relative to the source tree, e.g. `ext/standard`. Repeat for several
+  #[arg(long)]
+  filter: Vec<PathBuf>,
+
+  /// Parallel test workers (PHP 7.4+)
+  #[arg(short, long, default_value_t = num_cpus::get())]
+  jobs: usize,
+}
+
+/// `tests.json` stored next to `build.json`, counts of the last `maphp test` run
+#[derive(Debug, Default, Clone, PartialEq, Deserialize, Serialize)]
+pub struct TestSummary {
+  pub total: u64,
+  pub passed: u64,
+  pub failed: u64,
+  pub skipped: u64,
+  pub warned: u64,
+  pub expected_fail: u64,
+  pub borked: u64,
+  /// Directories or files the run was limited to
+  pub filter: Vec<PathBuf>,
+  /// Unix seconds of the run
+  pub ran_at: u64,
+}
+
+impl TestSummary {
+  pub fn path(src: impl AsRef<Path>) -> PathBuf {
+    src.as_ref().join("tests.json")
+  }
+
+  pub fn save(&self, src: impl AsRef<Path>) -> Maybe<()> {
+    crate::disk::atomic_write(Self::path(src), serde_json::to_vec_pretty(self)?)
+  }
+
+  /// Read the `TEST RESULT SUMMARY` block `run-tests.php` prints and saves with `-s`
+  fn parse(output: &str) -> Option<Self> {
+    let summary = &output[output.rfind("TEST RESULT SUMMARY")?..];
+    let mut this = Self::default();
+    for line in summary.lines() {
+      let Some((key, value)) = line.split_once(':') else { continue };
+      let Some(Ok(count)) = value.split_whitespace().next().map(str::parse) else {
+        continue;
+      };
+      match key.trim() {
+        "Number of tests" => this.total = count,
+        "Tests passed" => this.passed = count,
+        "Tests failed" => this.failed = count,
+        "Tests skipped" => this.skipped = count,
+        "Tests warned" => this.warned = count,
+        "Expected fail" => this.expected_fail = count,
+        "Tests borked" => this.borked = count,
+        _ => {}
+      }
+    }
+    Some(this)
+  }
+}
+
+impl TestArgs {
+  pub async fn handle(&self) -> Maybe<()> {
+    let source = SourcePHP::new(CLI.resolve(&self.version)?);
+    ensure!(source.is_installed(), "Version `{}` is not installed", source.name());
+    ensure!(
+      source.src().join("run-tests.php").is_file(),
+      "No run-tests.php in {}, the source tree isn't retained for versions installed from packages",
+      source.src().display()
+    );
+    if let Some(filter) = self.filter.iter().find(|filter| !source.src().join(filter).exists()) {
+      bail!("No tests at {} in the source tree", filter.display());
+    }
+
+    let php = source.dist().join("bin/php");
+    let log = source.src().join("tests.log");
+    let mut command = tokio::process::Command::new(&php);
+    command
+      .arg("run-tests.php")
+      .arg("-q")
+      .arg("-s")
+      .arg(&log)
+      .env("TEST_PHP_EXECUTABLE", &php)
+      .env("NO_INTERACTION", "1")
+      .current_dir(source.src())
+      .stdin(Stdio::null());
+    if source.dist().join("bin/php-cgi").is_file() {
+      command.env("TEST_PHP_CGI_EXECUTABLE", source.dist().join("bin/php-cgi"));
+    }
+    if self.jobs > 1 && source.version().is_some_and(|version| version.series() >= (7, 4)) {
+      command.arg(format!("-j{}", self.jobs));
+    }
+    command.args(&self.filter);
+
+    println!("Running the test suite of PHP {} with {} job(s)", source.name(), self.jobs);
+    command.status().await?;
+
+    let output = std::fs::read_to_string(&log).unwrap_or_default();
+    let Some(mut summary) = TestSummary::parse(&output) else {
+      bail!("run-tests.php didn't print a summary, see {}", log.display());
+    };
+    summary.filter = self.filter.clone();
+    summary.ran_at = crate::date::unix_now();
+    summary.save(source.src())?;
+
+    let counts = [
+      summary.passed,
+      summary.failed,
+      summary.skipped,
+      summary.warned,
+      summary.expected_fail,
+      summary.borked,
+      summary.total,
+    ];
+    print_table(
+      &["PASSED", "FAILED", "SKIPPED", "WARNED", "XFAIL", "BORKED", "TOTAL"],
+      &[counts.map(|count| count.to_string()).to_vec()],
+    );
+    println!("Results saved to {} and {}", TestSummary::path(source.src()).display(), log.display());
+
+    ensure!(
+      summary.failed == 0 && summary.borked == 0,
+      "{} test(s) failed",
+      summary.failed + summary.borked
+    );
+    Ok(())
+  }
+}
+
+#[test]
+fn test_parse_summary() {
+  let output = "\
+=====================================================================
+TEST RESULT SUMMARY
+---------------------------------------------------------------------
+Exts skipped    :   12
+Exts tested     :   58
+---------------------------------------------------------------------
+
+Number of tests : 18310             12377
+Tests skipped   : 5933 ( 32.4%) --------
+Tests warned    :    0 (  0.0%) (  0.0%)
+Tests failed    :    2 (  0.0%) (  0.0%)
+Expected fail   :   33 (  0.2%) (  0.3%)
+Tests passed    : 12342 ( 67.4%) ( 99.7%)
+---------------------------------------------------------------------
+Time taken      :  312 seconds
+=====================================================================
+";
+  let summary = TestSummary::parse(output).unwrap();
+  assert_eq!((summary.total, summary.passed, summary.failed), (18310, 12342, 2));
+  assert_eq!((summary.skipped, summary.expected_fail, summary.borked), (5933, 33, 0));
+  assert_eq!(TestSummary::parse("no summary"), None);
+}
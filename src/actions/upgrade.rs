@@ -0,0 +1,229 @@
+use crate::CLI;
+use crate::Maybe;
+use crate::actions::list::find_local_tags;
+use crate::alias::Aliases;
+use crate::config::Config;
+use crate::hooks::{self, Event, Operation, Phase};
+use crate::ini::conf_dir;
+use crate::output::{Format, FormatArgs, print_json, print_table, print_warning};
+use crate::source::SourcePHP;
+use crate::stats::Version;
+use anyhow::{Context, bail};
+use clap::Args;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+#[derive(Args, Clone, Debug)]
+pub struct OutdatedArgs {
+  #[command(flatten)]
+  format: FormatArgs,
+}
+
+#[derive(Args, Clone, Debug)]
+pub struct UpgradeArgs {
+  /// Minor series (e.g. `8.2`) or alias to upgrade. Every outdated series when omitted
+  #[arg(default_value = None)]
+  series: Option<String>,
+
+  /// Remove the previous patch once the new one is installed
+  #[arg(long, default_value_t = false)]
+  remove_old: bool,
+}
+
+/// An installed version with a newer stable patch in the same series
+#[derive(Debug)]
+pub struct Outdated {
+  pub current: SourcePHP,
+  pub latest: Version,
+}
+
+#[derive(Serialize)]
+struct OutdatedJson {
+  series: String,
+  version: String,
+  latest: String,
+}
+
+/// Compare the newest installed patch of each series against the newest known stable tag
+pub async fn find_outdated() -> Maybe<Vec<Outdated>> {
+  let tags = find_local_tags().await?;
+
+  let mut latest = BTreeMap::new();
+  for version in tags.iter().filter(|tag| tag.is_stable()).filter_map(|tag| tag.version()) {
+    let newest = latest.entry(version.series()).or_insert(version);
+    *newest = version.max(*newest);
+  }
+
+  let mut installed = BTreeMap::<_, (Version, SourcePHP)>::new();
+  for source in SourcePHP::scan_local(CLI.archives())? {
+    let Some(version) = source.version() else { continue };
+    match installed.get(&version.series()) {
+      Some((newest, _)) if *newest >= version => {}
+      _ => _ = installed.insert(version.series(), (version, source)),
+    }
+  }
+
+  Ok(
+    installed
+      .into_iter()
+      .rev()
+      .filter_map(|(series, (version, current))| {
+        let latest = *latest.get(&series)?;
+        (latest > version).then_some(Outdated { current, latest })
+      })
+      .collect(),
+  )
+}
+
+impl OutdatedArgs {
+  pub async fn handle(&self) -> Maybe<()> {
+    let outdated = find_outdated().await?;
+    let rows = outdated
+      .iter()
+      .map(|o| OutdatedJson {
+        series: format!("{}.{}", o.latest.major, o.latest.minor),
+        version: o.current.name().into_owned(),
+        latest: o.latest.to_string(),
+      })
+      .collect::<Vec<_>>();
+
+    match self.format.format() {
+      Format::Json => print_json(&rows)?,
+      Format::Plain => rows.iter().for_each(|row| println!("{} {}", row.version, row.latest)),
+      Format::Table if rows.is_empty() => println!("All installed versions are up to date"),
+      Format::Table => {
+        let rows = rows.into_iter().map(|row| vec![row.series, row.version, row.latest]).collect::<Vec<_>>();
+        print_table(&["SERIES", "INSTALLED", "LATEST"], &rows);
+      }
+    }
+
+    Ok(())
+  }
+}
+
+impl UpgradeArgs {
+  pub async fn handle(&self) -> Maybe<()> {
+    let series = match self.series {
+      Some(ref series) => {
+        let aliases = Aliases::load(CLI.aliases_file())?;
+        let version = aliases.get(series).unwrap_or(series);
+        Some(version.parse::<Version>().map_err(anyhow::Error::msg)?.series())
+      }
+      None => None,
+    };
+
+    let outdated = find_outdated()
+      .await?
+      .into_iter()
+      .filter(|o| series.is_none_or(|series| o.latest.series() == series))
+      .collect::<Vec<_>>();
+
+    if outdated.is_empty() {
+      println!("Nothing to upgrade");
+      return Ok(());
+    }
+
+    for Outdated { current, latest } in outdated {
+      println!("⬆️ Upgrading {} to {latest}", current.name());
+      upgrade(&current, &latest, self.remove_old)
+        .await
+        .with_context(|| format!("Couldn't upgrade {} to {latest}", current.name()))?;
+    }
+
+    Ok(())
+  }
+}
+
+/// Install `latest` with the options of `current`, then carry over its ini, extensions and active link
+async fn upgrade(current: &SourcePHP, latest: &Version, remove_old: bool) -> Maybe<()> {
+  let Some(options) = current.build_options() else {
+    bail!("No build options recorded for {}, reinstall it with `maphp install`", current.name());
+  };
+
+  let pre = [("MAPHP_NEW_VERSION", latest.to_string())];
+  hooks::run(Event(Phase::Pre, Operation::Upgrade), current, &pre)
+    .await?
+    .iter()
+    .for_each(print_warning);
+
+  // Installed with its install hooks, then verified again once the settings of `current` are carried over
+  let new = CLI.manager().install(&latest.to_string(), &options).await?;
+  let new = new.with_mode(CLI.manager().options().progress);
+
+  let carried = carry_over_ini(current, &new).await?;
+  if carried > 0 {
+    println!("✅ Carried over {carried} ini file(s)");
+  }
+
+  let available = new.shared_extensions();
+  for extension in current.shared_extensions().iter().filter(|ext| !available.contains(ext)) {
+    match new.pecl_install(extension).await {
+      Ok(()) => println!("✅ Reinstalled extension {extension}"),
+      Err(err) => print_warning(format!("Couldn't reinstall extension {extension}: {err:#}")),
+    }
+  }
+
+  crate::verify::verify(&new, &options)?;
+  println!("✅ Verified PHP {} with the carried over settings", new.name());
+
+  if current.is_in_path() {
+    CLI.manager().link(&new).await?;
+    println!("✅ {} is now active", new.name());
+  }
+
+  let mut config = Config::load(CLI.config_file())?;
+  if config.default.as_deref() == Some(&current.name()) {
+    config.default = Some(new.name().into_owned());
+    config.save(CLI.config_file())?;
+    println!("✅ {} is now the default version", new.name());
+  }
+
+  let mut aliases = Aliases::load(CLI.aliases_file())?;
+  let moved = aliases.retarget(&current.name(), &new.name());
+  if !moved.is_empty() {
+    aliases.save(CLI.aliases_file())?;
+    println!("✅ Moved alias(es) {} to {}", moved.join(", "), new.name());
+  }
+
+  if remove_old {
+    CLI.manager().remove(&[SourcePHP::new(current.src())]).await?;
+    println!("🗑️ Removed {}", current.name());
+  }
+
+  let post = [("MAPHP_OLD_VERSION", current.name().into_owned())];
+  hooks::run(Event(Phase::Post, Operation::Upgrade), &new, &post)
+    .await?
+    .iter()
+    .for_each(print_warning);
+  Ok(())
+}
+
+/// Copy `php.ini` and the `conf.d` snippets of `current` into `new`, paths to `current` now point to `new`.
+///
+/// Returns the number of copied files.
+async fn carry_over_ini(current: &SourcePHP, new: &SourcePHP) -> Maybe<usize> {
+  let mut files = vec![current.dist().join("lib/php.ini")];
+  if let Ok(entries) = std::fs::read_dir(conf_dir(current)) {
+    files.extend(
+      entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "ini")),
+    );
+  }
+
+  let mut copied = 0;
+  for file in files.iter().filter(|file| file.is_file()) {
+    let target = new.dist().join(file.strip_prefix(current.dist())?);
+    if let Some(parent) = target.parent() {
+      tokio::fs::create_dir_all(parent).await?;
+    }
+    tokio::fs::copy(file, &target).await?;
+    copied += 1;
+  }
+
+  if copied > 0 {
+    crate::package::relocate(&new.dist(), &current.dist(), &new.dist())?;
+  }
+  Ok(copied)
+}
@@ -0,0 +1,114 @@
+use crate::CLI;
+use crate::Maybe;
+use crate::ini::{Snippet, conf_dir, ensure_scanned, eval};
+use crate::output::print_warning;
+use crate::source::SourcePHP;
+use anyhow::bail;
+use clap::{Args, Subcommand};
+use colored::Colorize;
+
+const MODES: [&str; 7] = ["off", "develop", "coverage", "debug", "gcstats", "profile", "trace"];
+
+#[derive(Args, Clone, Debug)]
+pub struct XdebugArgs {
+  /// Shows the current state when omitted
+  #[command(subcommand)]
+  command: Option<XdebugCommand>,
+
+  /// Installed PHP version or alias, defaults to the active one
+  #[arg(long, global = true)]
+  php: Option<String>,
+}
+
+#[derive(Subcommand, Clone, Debug)]
+enum XdebugCommand {
+  /// Load Xdebug, it is installed with PECL when missing
+  On,
+  /// Stop loading Xdebug, its settings are kept
+  Off,
+  /// Set `xdebug.mode` and load Xdebug
+  Mode {
+    /// Comma separated modes: off, develop, coverage, debug, gcstats, profile, trace
+    #[arg(value_delimiter = ',', num_args = 1.., required = true)]
+    modes: Vec<String>,
+  },
+  /// Show whether Xdebug is loaded and its mode
+  Status,
+}
+
+impl XdebugArgs {
+  pub async fn handle(&self) -> Maybe<()> {
+    let source = CLI.target(self.php.as_deref())?;
+    let path = conf_dir(&source).join("xdebug.ini");
+    let mut snippet = Snippet::load(&path)?;
+
+    match self.command {
+      None | Some(XdebugCommand::Status) => return status(&source, &snippet),
+      Some(XdebugCommand::On) => {
+        ensure_scanned(&source)?;
+        install(&source).await?;
+        snippet.set("zend_extension", "xdebug");
+        if snippet.get("xdebug.mode").is_none() {
+          snippet.set("xdebug.mode", "debug");
+        }
+      }
+      Some(XdebugCommand::Off) => snippet.disable("zend_extension"),
+      Some(XdebugCommand::Mode { ref modes }) => {
+        if let Some(mode) = modes.iter().find(|mode| !MODES.contains(&mode.as_str())) {
+          bail!("Unknown Xdebug mode `{mode}`, expected one of {}", MODES.join(", "));
+        }
+        ensure_scanned(&source)?;
+        install(&source).await?;
+        snippet.set("zend_extension", "xdebug");
+        snippet.set("xdebug.mode", modes.join(","));
+      }
+    }
+
+    snippet.save(&path, "xdebug")?;
+    status(&source, &snippet)
+  }
+}
+
+/// Build Xdebug with PECL unless the version already has it, older PHP gets the last release supporting it
+async fn install(source: &SourcePHP) -> Maybe<()> {
+  if source.shared_extensions().iter().any(|ext| ext == "xdebug") {
+    return Ok(());
+  }
+
+  let package = match source.version().map(|version| version.series()) {
+    Some(series) if series < (7, 2) => bail!("Xdebug 3 requires PHP 7.2 or newer"),
+    Some(series) if series < (8, 0) => "xdebug-3.1.6",
+    Some(series) if series < (8, 1) => "xdebug-3.3.2",
+    _ => "xdebug",
+  };
+  source.pecl_install(package).await?;
+  println!("✅ Xdebug installed for PHP {}", source.name());
+  Ok(())
+}
+
+/// `on` only when the snippet loading Xdebug is itself loaded
+fn status(source: &SourcePHP, snippet: &Snippet) -> Maybe<()> {
+  let mut enabled = snippet.get("zend_extension").is_some();
+  if enabled && let Err(err) = ensure_scanned(source) {
+    print_warning(format!("xdebug.ini isn't loaded: {err}"));
+    enabled = false;
+  }
+  let mode = snippet.get("xdebug.mode").unwrap_or("develop");
+  match enabled {
+    true => println!("Xdebug of PHP {}: {} (mode {mode})", source.name(), "on".green()),
+    false => println!("Xdebug of PHP {}: {}", source.name(), "off".yellow()),
+  }
+
+  let loaded = eval(source, "extension_loaded('xdebug') ? 'on' : 'off'");
+  if loaded
+    .as_deref()
+    .is_some_and(|loaded| (loaded == "on" && !enabled) || (loaded == "off" && enabled))
+  {
+    println!(
+      "{} php reports Xdebug {}, check `php --ini` for another ini loading it",
+      "⚠️".yellow(),
+      loaded.unwrap_or_default()
+    );
+  }
+  Ok(())
+}
@@ -0,0 +1,90 @@
+use crate::Maybe;
+use crate::stats::Version;
+use anyhow::ensure;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Names referring to installed versions, stored as `aliases.json` in the work dir
+#[derive(Debug, Clone, Default)]
+pub struct Aliases(BTreeMap<String, String>);
+
+impl Aliases {
+  /// `aliases.json` of a work dir
+  pub fn path(work_dir: impl AsRef<Path>) -> std::path::PathBuf {
+    work_dir.as_ref().join("aliases.json")
+  }
+
+  pub fn load(path: impl AsRef<Path>) -> Maybe<Self> {
+    match std::fs::read(path) {
+      Ok(content) => Ok(Self(serde_json::from_slice(&content)?)),
+      Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+      Err(err) => Err(err.into()),
+    }
+  }
+
+  pub fn save(&self, path: impl AsRef<Path>) -> Maybe<()> {
+    crate::disk::atomic_write(path, serde_json::to_vec_pretty(&self.0)?)
+  }
+
+  /// Name must not look like a version so it can never shadow one
+  pub fn validate(name: &str) -> Maybe<()> {
+    ensure!(
+      !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'),
+      "Alias `{name}` may only contain letters, digits, `-` and `_`"
+    );
+    ensure!(name.parse::<Version>().is_err(), "Alias `{name}` looks like a version");
+    Ok(())
+  }
+
+  pub fn get(&self, name: &str) -> Option<&str> {
+    self.0.get(name).map(String::as_str)
+  }
+
+  pub fn set(&mut self, name: impl Into<String>, version: impl Into<String>) -> Option<String> {
+    self.0.insert(name.into(), version.into())
+  }
+
+  pub fn remove(&mut self, name: &str) -> Option<String> {
+    self.0.remove(name)
+  }
+
+  pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+    self.0.iter().map(|(name, version)| (name.as_str(), version.as_str()))
+  }
+
+  /// Aliases pointing at `version`
+  pub fn names_of(&self, version: &str) -> Vec<String> {
+    self.iter().filter(|(_, v)| *v == version).map(|(name, _)| name.to_owned()).collect()
+  }
+
+  /// Point every alias of `from` to `to`, returns the moved names
+  pub fn retarget(&mut self, from: &str, to: &str) -> Vec<String> {
+    let names = self.names_of(from);
+    for name in &names {
+      self.0.insert(name.clone(), to.to_owned());
+    }
+    names
+  }
+
+  /// Drop every alias of `version`, returns the removed names
+  pub fn remove_version(&mut self, version: &str) -> Vec<String> {
+    let names = self.names_of(version);
+    self.0.retain(|_, v| v != version);
+    names
+  }
+}
+
+#[test]
+fn test_aliases() {
+  assert!(Aliases::validate("prod").is_ok());
+  assert!(Aliases::validate("8.2").is_err());
+  assert!(Aliases::validate("my alias").is_err());
+
+  let mut aliases = Aliases::default();
+  aliases.set("prod", "8.2.20");
+  aliases.set("legacy", "7.4.33");
+  assert_eq!(aliases.retarget("8.2.20", "8.2.21"), ["prod"]);
+  assert_eq!(aliases.get("prod"), Some("8.2.21"));
+  assert_eq!(aliases.remove_version("7.4.33"), ["legacy"]);
+  assert_eq!(aliases.iter().count(), 1);
+}
@@ -0,0 +1,38 @@
+use crate::Maybe;
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// User settings stored as `config.json` in the work dir
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Config {
+  /// Version linked when the active one is removed
+  pub default: Option<String>,
+  /// Binary repository, a URL or a directory holding `index.json`, checked before building from source
+  pub repository: Option<String>,
+  /// Shell commands per event such as `post-install`, run after the scripts in `hooks/`
+  pub hooks: BTreeMap<String, Vec<String>>,
+  /// A failing hook aborts the operation instead of only being reported
+  pub abort_on_hook_failure: bool,
+}
+
+impl Config {
+  /// `config.json` of a work dir
+  pub fn path(work_dir: impl AsRef<Path>) -> PathBuf {
+    work_dir.as_ref().join("config.json")
+  }
+
+  pub fn load(path: impl AsRef<Path>) -> Maybe<Self> {
+    match std::fs::read(path.as_ref()) {
+      Ok(content) => serde_json::from_slice(&content).with_context(|| format!("Invalid config {}", path.as_ref().display())),
+      Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+      Err(err) => Err(err.into()),
+    }
+  }
+
+  pub fn save(&self, path: impl AsRef<Path>) -> Maybe<()> {
+    crate::disk::atomic_write(path, serde_json::to_vec_pretty(self)?)
+  }
+}
@@ -0,0 +1,44 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Seconds since the unix epoch
+pub fn unix_now() -> u64 {
+  SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default()
+}
+
+/// Current UTC date as `YYYY-MM-DD`
+pub fn today() -> String {
+  rfc3339(unix_now())[..10].to_owned()
+}
+
+/// Convert days since the unix epoch into a `(year, month, day)` civil date
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+  let z = days + 719_468;
+  let era = z.div_euclid(146_097);
+  let doe = z.rem_euclid(146_097);
+  let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+  let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+  let mp = (5 * doy + 2) / 153;
+  let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+  let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+  let year = yoe + era * 400 + i64::from(month <= 2);
+  (year, month, day)
+}
+
+/// Format unix seconds as an RFC 3339 UTC timestamp, e.g. `2024-11-21T10:00:00Z`
+pub fn rfc3339(secs: u64) -> String {
+  let (year, month, day) = civil_from_days((secs / 86_400) as i64);
+  let rem = secs % 86_400;
+  format!("{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z", rem / 3600, rem % 3600 / 60, rem % 60)
+}
+
+/// Format a [`SystemTime`] as an RFC 3339 UTC timestamp
+pub fn format_system_time(time: SystemTime) -> String {
+  rfc3339(time.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default())
+}
+
+#[test]
+fn test_rfc3339() {
+  assert_eq!(rfc3339(0), "1970-01-01T00:00:00Z");
+  assert_eq!(rfc3339(1_732_183_200), "2024-11-21T10:00:00Z");
+  assert_eq!(rfc3339(951_782_400), "2000-02-29T00:00:00Z");
+}
@@ -0,0 +1,184 @@
+use serde::Serialize;
+use std::path::{Component, Path, PathBuf};
+
+/// `parent/name` when `name` is a single plain path component, so the result can't escape `parent`
+pub fn child(parent: &Path, name: &str) -> Option<PathBuf> {
+  let mut components = Path::new(name).components();
+  match (components.next(), components.next()) {
+    (Some(Component::Normal(_)), None) => Some(parent.join(name)).filter(|path| path.parent() == Some(parent)),
+    _ => None,
+  }
+}
+
+/// Total size in bytes of every file under `path`, symlinks are not followed
+pub fn dir_size(path: impl AsRef<Path>) -> u64 {
+  let mut size = 0;
+  walk(path.as_ref(), &mut |_, len| size += len);
+  size
+}
+
+/// Write `content` next to `path` then rename it over `path`, readers never see a partially written file
+pub fn atomic_write(path: impl AsRef<Path>, content: impl AsRef<[u8]>) -> crate::Maybe<()> {
+  use std::io::Write;
+
+  let path = path.as_ref();
+  let mut tmp = path.as_os_str().to_owned();
+  tmp.push(".tmp");
+
+  let mut file = std::fs::File::create(&tmp)?;
+  file.write_all(content.as_ref())?;
+  file.sync_all()?;
+  std::fs::rename(&tmp, path)?;
+
+  Ok(())
+}
+
+/// Copy `from` into `to` recursively, symlinks are copied as links and permissions kept
+pub fn copy_tree(from: &Path, to: &Path) -> crate::Maybe<()> {
+  std::fs::create_dir_all(to)?;
+  std::fs::set_permissions(to, std::fs::metadata(from)?.permissions())?;
+
+  for entry in std::fs::read_dir(from)?.flatten() {
+    let target = to.join(entry.file_name());
+    let kind = entry.file_type()?;
+    if kind.is_dir() {
+      copy_tree(&entry.path(), &target)?;
+    } else if kind.is_symlink() {
+      #[cfg(unix)]
+      std::os::unix::fs::symlink(std::fs::read_link(entry.path())?, &target)?;
+    } else {
+      std::fs::copy(entry.path(), &target)?;
+    }
+  }
+
+  Ok(())
+}
+
+/// Disk usage of a source tree split by kind of file
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct Usage {
+  /// PHP sources and anything not matched below
+  pub source: u64,
+  /// Object files and libraries produced by `make`, not needed once installed
+  pub build: u64,
+  /// Installed runtime under `dist`
+  pub dist: u64,
+  /// `*.log` files, e.g. `config.log`
+  pub logs: u64,
+  /// Downloaded or packed tarballs
+  pub tarballs: u64,
+}
+
+impl Usage {
+  /// Walk `src`, `dist` is counted as a whole
+  pub fn of(src: impl AsRef<Path>) -> Self {
+    let src = src.as_ref();
+    let mut usage = Self {
+      dist: dir_size(src.join("dist")),
+      ..Self::default()
+    };
+
+    walk(src, &mut |path, len| {
+      if path.starts_with(src.join("dist")) {
+        return;
+      }
+      let slot = if is_build_artifact(path) {
+        &mut usage.build
+      } else if path.extension().is_some_and(|ext| ext == "log") {
+        &mut usage.logs
+      } else if is_tarball(path) {
+        &mut usage.tarballs
+      } else {
+        &mut usage.source
+      };
+      *slot += len;
+    });
+
+    usage
+  }
+
+  pub fn total(&self) -> u64 {
+    self.source + self.build + self.dist + self.logs + self.tarballs
+  }
+
+  pub fn add(&mut self, other: &Self) {
+    self.source += other.source;
+    self.build += other.build;
+    self.dist += other.dist;
+    self.logs += other.logs;
+    self.tarballs += other.tarballs;
+  }
+}
+
+/// Call `visit` with every regular file under `path` and its size, symlinks are not followed
+pub fn walk(path: &Path, visit: &mut impl FnMut(&Path, u64)) {
+  let Ok(entries) = std::fs::read_dir(path) else { return };
+
+  for entry in entries.flatten() {
+    match entry.file_type() {
+      Ok(kind) if kind.is_dir() => walk(&entry.path(), visit),
+      Ok(kind) if kind.is_file() => visit(&entry.path(), entry.metadata().map(|m| m.len()).unwrap_or_default()),
+      _ => {}
+    }
+  }
+}
+
+/// What `make clean` would delete: objects, libtool archives and `.libs` directories
+fn is_build_artifact(path: &Path) -> bool {
+  const EXTENSIONS: [&str; 4] = ["o", "lo", "a", "la"];
+
+  path.extension().and_then(|ext| ext.to_str()).is_some_and(|ext| EXTENSIONS.contains(&ext))
+    || path.parent().and_then(Path::file_name).is_some_and(|dir| dir == ".libs")
+}
+
+fn is_tarball(path: &Path) -> bool {
+  let name = path.file_name().map(|name| name.to_string_lossy()).unwrap_or_default();
+  [".tar.gz", ".tgz", ".tar.zst", ".tar.xz", ".tar.bz2"]
+    .iter()
+    .any(|ext| name.ends_with(ext))
+}
+
+/// Delete build artifacts under `src` outside of `dist`, returns the bytes freed
+pub fn remove_build_artifacts(src: impl AsRef<Path>) -> crate::Maybe<u64> {
+  let src = src.as_ref();
+  let mut artifacts = Vec::new();
+  walk(src, &mut |path, len| {
+    if !path.starts_with(src.join("dist")) && is_build_artifact(path) {
+      artifacts.push((path.to_path_buf(), len));
+    }
+  });
+
+  let mut freed = 0;
+  for (path, len) in artifacts {
+    std::fs::remove_file(&path)?;
+    freed += len;
+  }
+
+  Ok(freed)
+}
+
+#[test]
+fn test_usage() {
+  let dir = std::env::temp_dir().join(format!("maphp-usage-{}", std::process::id()));
+  for (file, len) in [
+    ("main/main.c", 10),
+    ("main/main.lo", 20),
+    ("main/.libs/main.o", 30),
+    ("config.log", 40),
+    ("php-8.3.0.tar.gz", 50),
+    ("dist/bin/php", 60),
+  ] {
+    let path = dir.join(file);
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(path, vec![0; len]).unwrap();
+  }
+
+  let usage = Usage::of(&dir);
+  assert_eq!((usage.source, usage.build, usage.dist, usage.logs, usage.tarballs), (10, 50, 60, 40, 50));
+  assert_eq!(usage.total(), dir_size(&dir));
+
+  assert_eq!(remove_build_artifacts(&dir).unwrap(), 50);
+  assert!(dir.join("dist/bin/php").is_file());
+  assert_eq!(Usage::of(&dir).build, 0);
+  std::fs::remove_dir_all(dir).unwrap();
+}
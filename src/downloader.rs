@@ -1,47 +1,46 @@
-use crate::static_const::{CLI, DOWNLOAD_HEADERS};
+use crate::progress::{ProgressMode, Reporter};
 use crate::static_const::{DOWNLOAD_URL, MIN_TAR_SIZE};
-use crate::{Maybe, dl_template};
+use crate::{Maybe, github};
 use anyhow::bail;
 use bytes::Bytes;
 use flate2::read::GzDecoder;
 use futures_util::stream::BoxStream;
 use futures_util::{Stream, StreamExt};
-use indicatif::{HumanBytes, ProgressBar};
+use indicatif::HumanBytes;
 use std::fs;
 use std::path::{Component, Path, PathBuf};
 use std::pin::Pin;
 use std::task::Poll::Ready;
 use std::task::{Context, Poll, ready};
-use std::time::Duration;
 use tar::{Entries, Unpacked};
 
 type Response = Result<Bytes, reqwest::Error>;
 
 pub struct Downloader {
-  progress: Option<ProgressBar>,
+  progress: Reporter,
+  /// Mode of the reporter when it belongs to this download and should be finished with it
+  owned: Option<ProgressMode>,
   stream: BoxStream<'static, Response>,
   archive: Option<Vec<u8>>,
 }
 
 impl Downloader {
-  fn new_progress() -> ProgressBar {
-    let progress = ProgressBar::new_spinner();
-    progress.set_style(dl_template());
-    progress.enable_steady_tick(Duration::from_secs_f32(crate::static_const::FPS));
-    progress
+  pub async fn new(tag: &str, mode: ProgressMode) -> Maybe<Self> {
+    let progress = Reporter::download(mode);
+    progress.set_prefix(format!("Downloading {tag}"));
+    let mut downloader = Self::with_progress(tag, progress).await?;
+    downloader.owned = Some(mode);
+    Ok(downloader)
   }
 
-  pub async fn new(tag: &str) -> Maybe<Self> {
+  /// Download `tag` while reporting on an existing reporter, which is left unfinished
+  pub async fn with_progress(tag: &str, progress: Reporter) -> Maybe<Self> {
     let url = DOWNLOAD_URL.join(&format!("php-{tag}"))?;
-    let response = reqwest::Client::new()
-      .get(url)
-      .headers(DOWNLOAD_HEADERS.clone())
-      .send()
-      .await?
-      .error_for_status()?;
+    let response = github::send(github::client()?.get(url)).await?.error_for_status()?;
 
     Ok(Self {
-      progress: Self::new_progress().into(),
+      progress,
+      owned: None,
       stream: Box::pin(response.bytes_stream()),
       archive: None,
     })
@@ -58,20 +57,26 @@ impl Downloader {
     Ok(())
   }
 
-  pub fn extract(&mut self, tag: &str, verbose: bool) -> Maybe<PathBuf> {
+  /// Extract the downloaded tarball of `tag` into `path`
+  pub fn extract(&mut self, tag: &str, path: PathBuf, verbose: bool) -> Maybe<PathBuf> {
     let Some(archive) = self.archive.take() else {
       bail!("No archive have been downloaded")
     };
 
-    println!("Downloaded {}", HumanBytes(archive.len() as u64));
-
-    let path = CLI.archives().join(tag);
     let mut tar = tar::Archive::new(GzDecoder::new(archive.as_slice()));
     tar.set_overwrite(true);
     tar.set_preserve_permissions(true);
     tar.set_preserve_mtime(true);
     let entries = tar.entries()?;
-    extract_unwrap(entries, &path, verbose)?;
+
+    if let Some(mode) = self.owned {
+      extract_unwrap(entries, &path, verbose, mode)?;
+    } else {
+      self
+        .progress
+        .set_prefix(format!("{tag}: extracting {}", HumanBytes(archive.len() as u64)));
+      extract_with(entries, &path, false, &self.progress)?;
+    }
 
     Ok(path)
   }
@@ -83,13 +88,13 @@ impl Stream for Downloader {
     let this = self.get_mut();
     let item = ready!(this.stream.poll_next_unpin(cx));
 
-    if let Some(progress) = this.progress.as_ref() {
-      if let Some(Ok(bytes)) = item.as_ref() {
-        progress.inc(bytes.len() as u64);
-      } else {
-        progress.finish();
-        this.progress.take();
-      }
+    if let Some(Ok(bytes)) = item.as_ref() {
+      this.progress.inc(bytes.len() as u64);
+      this.progress.set_message(HumanBytes(this.progress.position()).to_string());
+    } else if this.owned.is_some() {
+      this
+        .progress
+        .finish_with_message(format!("Downloaded {}", HumanBytes(this.progress.position())));
     }
 
     Ready(item)
@@ -97,7 +102,20 @@ impl Stream for Downloader {
 }
 
 #[allow(dead_code)]
-fn extract_unwrap<R>(entries: Entries<'_, R>, dst: impl AsRef<Path>, verbose: bool) -> Maybe<()>
+fn extract_unwrap<R>(entries: Entries<'_, R>, dst: impl AsRef<Path>, verbose: bool, mode: ProgressMode) -> Maybe<()>
+where
+  R: std::io::Read,
+{
+  let progress = Reporter::spinner(mode);
+  progress.set_prefix(format!("Extracting into {}", dst.as_ref().display()));
+  let total = extract_with(entries, dst, verbose, &progress)?;
+  progress.finish_with_message(format!("Extracted {}", HumanBytes(total)));
+
+  Ok(())
+}
+
+/// Unpack `entries` into `dst` reporting on `progress`, returns the total extracted bytes
+fn extract_with<R>(entries: Entries<'_, R>, dst: impl AsRef<Path>, verbose: bool, progress: &Reporter) -> Maybe<u64>
 where
   R: std::io::Read,
 {
@@ -106,9 +124,7 @@ where
   }
 
   let dst = dst.as_ref();
-  let progress = Downloader::new_progress();
   let mut total = 0;
-  progress.println("Extracting..");
 
   for mut entry in entries.flatten() {
     let relative_path = get_extract_path(dst, entry.header())?;
@@ -131,9 +147,7 @@ where
     }
   }
 
-  progress.println(format!("Extracted {}", HumanBytes(total)));
-
-  Ok(())
+  Ok(total)
 }
 
 const PREFIX: &str = "php-php-src";
@@ -167,6 +181,6 @@ mod tests {
     tar.set_preserve_permissions(true);
     tar.set_preserve_mtime(true);
     let entries = tar.entries().unwrap();
-    super::extract_unwrap(entries, TEST_DIR, true).unwrap();
+    super::extract_unwrap(entries, TEST_DIR, true, super::ProgressMode::Plain).unwrap();
   }
 }
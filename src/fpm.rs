@@ -0,0 +1,219 @@
+use crate::Maybe;
+use crate::source::SourcePHP;
+use anyhow::{bail, ensure};
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::time::Duration;
+
+/// Header of the files maphp writes, they are regenerated on every start
+const GENERATED: &str = "; Generated by maphp, regenerated on `maphp fpm start`. Add your own pools to php-fpm.d/*.conf";
+
+/// php-fpm of one installed version, its pid file and socket live in `run/<version>`, logs in `logs/<version>`
+pub struct Fpm<'a> {
+  source: &'a SourcePHP,
+}
+
+impl<'a> Fpm<'a> {
+  pub fn new(source: &'a SourcePHP) -> Self {
+    Self { source }
+  }
+
+  /// Whether this version was built with `--enable-fpm`
+  pub fn is_available(&self) -> bool {
+    self.binary().is_file()
+  }
+
+  fn binary(&self) -> PathBuf {
+    self.source.dist().join("sbin/php-fpm")
+  }
+
+  pub fn config(&self) -> PathBuf {
+    self.source.dist().join("etc/php-fpm.conf")
+  }
+
+  fn pool(&self) -> PathBuf {
+    self.source.dist().join("etc/php-fpm.d/maphp.conf")
+  }
+
+  pub fn run_dir(&self) -> PathBuf {
+    self.source.work_dir().join("run").join(&*self.source.name())
+  }
+
+  pub fn log_dir(&self) -> PathBuf {
+    self.source.work_dir().join("logs").join(&*self.source.name())
+  }
+
+  pub fn pid_file(&self) -> PathBuf {
+    self.run_dir().join("php-fpm.pid")
+  }
+
+  /// Unix socket the pool listens on
+  pub fn socket(&self) -> PathBuf {
+    self.run_dir().join("php-fpm.sock")
+  }
+
+  /// Write `php-fpm.conf` and the `maphp` pool for the current work dir
+  pub fn write_config(&self) -> Maybe<()> {
+    std::fs::create_dir_all(self.run_dir())?;
+    std::fs::create_dir_all(self.log_dir())?;
+    std::fs::create_dir_all(self.pool().parent().unwrap_or(&self.run_dir()))?;
+
+    let global = format!(
+      "{GENERATED}\n[global]\npid = {pid}\nerror_log = {log}\ndaemonize = yes\n\ninclude = {pools}\n",
+      pid = self.pid_file().display(),
+      log = self.log_dir().join("php-fpm.log").display(),
+      pools = self.source.dist().join("etc/php-fpm.d/*.conf").display(),
+    );
+    let pool = format!(
+      "{GENERATED}\n[{name}]\nlisten = {socket}\nlisten.mode = 0660\n\npm = dynamic\npm.max_children = 5\npm.start_servers = 2\npm.min_spare_servers = 1\npm.max_spare_servers = 3\n\naccess.log = {access}\ncatch_workers_output = yes\n",
+      name = self.source.name(),
+      socket = self.socket().display(),
+      access = self.log_dir().join("access.log").display(),
+    );
+
+    crate::disk::atomic_write(self.config(), global)?;
+    crate::disk::atomic_write(self.pool(), pool)?;
+    Ok(())
+  }
+
+  /// Pid of the running master process, which may belong to another user
+  pub fn pid(&self) -> Option<i32> {
+    let pid = parse_pid(&std::fs::read_to_string(self.pid_file()).ok()?)?;
+    is_running(pid).then_some(pid)
+  }
+
+  pub fn start(&self) -> Maybe<i32> {
+    ensure!(
+      self.is_available(),
+      "PHP {} was built without FPM, reinstall it with --enable-fpm",
+      self.source.name()
+    );
+    if let Some(pid) = self.pid() {
+      bail!("php-fpm {} is already running (pid {pid})", self.source.name());
+    }
+
+    self.write_config()?;
+    let command = || {
+      let mut command = std::process::Command::new(self.binary());
+      command.arg("--fpm-config").arg(self.config()).stdin(Stdio::null());
+      // php-fpm refuses to run as root unless told to, workers then run as root too
+      if unsafe { libc::geteuid() } == 0 {
+        command.arg("--allow-to-run-as-root");
+      }
+      command
+    };
+
+    let test = command().arg("--test").output()?;
+    ensure!(
+      test.status.success(),
+      "Invalid php-fpm configuration: {}",
+      String::from_utf8_lossy(&test.stderr).trim()
+    );
+
+    let status = command().status()?;
+    ensure!(
+      status.success(),
+      "php-fpm exited with {status}, see {}",
+      self.log_dir().join("php-fpm.log").display()
+    );
+
+    for _ in 0..50 {
+      if let Some(pid) = self.pid() {
+        return Ok(pid);
+      }
+      std::thread::sleep(Duration::from_millis(100));
+    }
+    bail!("php-fpm didn't start, see {}", self.log_dir().join("php-fpm.log").display())
+  }
+
+  /// Graceful stop, workers finish their requests first. Killed after 10 seconds.
+  pub fn stop(&self) -> Maybe<bool> {
+    let Some(pid) = self.pid() else { return Ok(false) };
+
+    signal(pid, libc::SIGQUIT)?;
+    if !wait_exit(pid, 100) {
+      signal(pid, libc::SIGKILL)?;
+      ensure!(
+        wait_exit(pid, 20),
+        "php-fpm {} (pid {pid}) is still running after SIGKILL",
+        self.source.name()
+      );
+    }
+
+    _ = std::fs::remove_file(self.pid_file());
+    Ok(true)
+  }
+}
+
+/// Pid written in a pid file, only positive ones as `kill` takes 0 and negative pids for process groups
+fn parse_pid(content: &str) -> Option<i32> {
+  content.trim().parse().ok().filter(|pid| *pid > 0)
+}
+
+/// Whether process `pid` exists. Signal 0 only checks it, `EPERM` means it runs as another user
+pub(crate) fn is_running(pid: i32) -> bool {
+  pid > 0 && (unsafe { libc::kill(pid, 0) } == 0 || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM))
+}
+
+fn signal(pid: i32, signal: i32) -> Maybe<()> {
+  if unsafe { libc::kill(pid, signal) } == 0 {
+    return Ok(());
+  }
+  match std::io::Error::last_os_error() {
+    err if err.raw_os_error() == Some(libc::ESRCH) => Ok(()),
+    err if err.raw_os_error() == Some(libc::EPERM) => bail!("php-fpm (pid {pid}) runs as another user, it can't be stopped"),
+    err => Err(err.into()),
+  }
+}
+
+/// Poll every 100ms, up to `attempts` times, until `pid` is gone
+fn wait_exit(pid: i32, attempts: usize) -> bool {
+  for _ in 0..attempts {
+    if !is_running(pid) {
+      return true;
+    }
+    std::thread::sleep(Duration::from_millis(100));
+  }
+  !is_running(pid)
+}
+
+/// Installed versions in `archives` built with FPM
+pub fn available(archives: impl AsRef<std::path::Path>) -> Maybe<Vec<SourcePHP>> {
+  let mut sources = SourcePHP::scan_local(archives)?
+    .into_iter()
+    .filter(|source| Fpm::new(source).is_available())
+    .collect::<Vec<_>>();
+  sources.sort_by_key(|source| std::cmp::Reverse(source.version()));
+  Ok(sources)
+}
+
+#[test]
+fn test_parse_pid() {
+  assert_eq!(parse_pid("4242\n"), Some(4242));
+  for content in ["", "0", "-1", "-4242", "abc", "42 43"] {
+    assert_eq!(parse_pid(content), None, "{content:?}");
+  }
+}
+
+#[test]
+fn test_pid() {
+  let work_dir = std::env::temp_dir().join(format!("maphp-fpm-{}", std::process::id()));
+  let source = SourcePHP::new(work_dir.join("archives/8.3.0"));
+  let fpm = Fpm::new(&source);
+  std::fs::create_dir_all(fpm.run_dir()).unwrap();
+
+  let mut child = std::process::Command::new("true").spawn().unwrap();
+  child.wait().unwrap();
+  let stale = child.id().to_string();
+
+  for content in ["0", "-1", "garbage", stale.as_str()] {
+    std::fs::write(fpm.pid_file(), content).unwrap();
+    assert_eq!(fpm.pid(), None, "{content:?}");
+    assert!(!fpm.stop().unwrap());
+  }
+
+  std::fs::write(fpm.pid_file(), std::process::id().to_string()).unwrap();
+  assert_eq!(fpm.pid(), Some(std::process::id() as i32));
+
+  std::fs::remove_dir_all(work_dir).unwrap();
+}
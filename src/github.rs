@@ -0,0 +1,88 @@
+use crate::Maybe;
+use crate::static_const::USER_AGENT;
+use anyhow::bail;
+use reqwest::header::{ACCEPT, AUTHORIZATION, HeaderMap, HeaderValue, RETRY_AFTER};
+use reqwest::{Client, RequestBuilder, Response, StatusCode};
+use std::time::Duration;
+
+/// Longest rate limit reset we are willing to sleep through before giving up
+const MAX_WAIT: Duration = Duration::from_secs(90);
+const MAX_ATTEMPTS: usize = 3;
+
+/// API token from `MAPHP_GITHUB_TOKEN` or `GITHUB_TOKEN`
+pub fn token() -> Option<String> {
+  ["MAPHP_GITHUB_TOKEN", "GITHUB_TOKEN"]
+    .into_iter()
+    .filter_map(|name| std::env::var(name).ok())
+    .map(|token| token.trim().to_owned())
+    .find(|token| !token.is_empty())
+}
+
+/// HTTP client for api.github.com, authenticated when a token is available
+pub fn client() -> Maybe<Client> {
+  let mut headers = HeaderMap::new();
+  headers.insert(ACCEPT, HeaderValue::from_static("application/vnd.github+json"));
+  headers.insert("X-GitHub-Api-Version", HeaderValue::from_static("2022-11-28"));
+  if let Some(token) = token() {
+    let mut value = HeaderValue::from_str(&format!("Bearer {token}"))?;
+    value.set_sensitive(true);
+    headers.insert(AUTHORIZATION, value);
+  }
+
+  Ok(Client::builder().user_agent(USER_AGENT).default_headers(headers).build()?)
+}
+
+/// Send `request`, waiting for the rate limit to reset when it is close enough, otherwise failing with a helpful error.
+pub async fn send(request: RequestBuilder) -> Maybe<Response> {
+  let mut attempt = 1;
+
+  loop {
+    let Some(retry) = request.try_clone() else {
+      return Ok(request.send().await?);
+    };
+    let response = retry.send().await?;
+
+    let Some(wait) = rate_limited(&response) else {
+      return Ok(response);
+    };
+
+    if wait > MAX_WAIT || attempt >= MAX_ATTEMPTS {
+      let hint = if token().is_none() {
+        ", set GITHUB_TOKEN to raise the limit"
+      } else {
+        ""
+      };
+      bail!("GitHub API rate limit exceeded, it resets in {}s{hint}", wait.as_secs());
+    }
+
+    eprintln!("⏳ GitHub API rate limit reached, retrying in {}s", wait.as_secs());
+    tokio::time::sleep(wait).await;
+    attempt += 1;
+  }
+}
+
+/// How long to wait before retrying, `None` if the response isn't rate limited
+fn rate_limited(response: &Response) -> Option<Duration> {
+  if !matches!(response.status(), StatusCode::FORBIDDEN | StatusCode::TOO_MANY_REQUESTS) {
+    return None;
+  }
+
+  let header = |name: &str| {
+    response
+      .headers()
+      .get(name)
+      .and_then(|v| v.to_str().ok())
+      .and_then(|v| v.parse::<u64>().ok())
+  };
+
+  if let Some(seconds) = header(RETRY_AFTER.as_str()) {
+    return Some(Duration::from_secs(seconds));
+  }
+
+  if header("x-ratelimit-remaining") == Some(0) {
+    let reset = header("x-ratelimit-reset").unwrap_or_default();
+    return Some(Duration::from_secs(reset.saturating_sub(crate::date::unix_now()).max(1)));
+  }
+
+  None
+}
@@ -0,0 +1,93 @@
+use crate::Maybe;
+use crate::config::Config;
+use crate::source::SourcePHP;
+use anyhow::bail;
+use std::fmt::{Display, Formatter};
+use tokio::process::Command;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+  Pre,
+  Post,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+  Install,
+  Use,
+  Remove,
+  Upgrade,
+}
+
+/// Lifecycle event a hook is registered for, e.g. `post-install`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Event(pub Phase, pub Operation);
+
+impl Display for Event {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    let phase = match self.0 {
+      Phase::Pre => "pre",
+      Phase::Post => "post",
+    };
+    let operation = match self.1 {
+      Operation::Install => "install",
+      Operation::Use => "use",
+      Operation::Remove => "remove",
+      Operation::Upgrade => "upgrade",
+    };
+    write!(f, "{phase}-{operation}")
+  }
+}
+
+/// Run the `hooks/<event>` script of the work dir then the commands configured for `event`.
+///
+/// Hooks get `MAPHP_EVENT`, `MAPHP_PHASE`, `MAPHP_OPERATION`, `MAPHP_VERSION`, `MAPHP_SRC`, `MAPHP_DIST`
+/// and `MAPHP_WORK_DIR` plus `extra`. Failures abort when `abort_on_hook_failure` is set, otherwise they're returned.
+pub async fn run(event: Event, source: &SourcePHP, extra: &[(&str, String)]) -> Maybe<Vec<String>> {
+  let config = Config::load(Config::path(source.work_dir()))?;
+  let name = event.to_string();
+
+  let mut hooks = vec![];
+  let script = source.work_dir().join("hooks").join(&name);
+  if script.is_file() {
+    hooks.push((script.display().to_string(), Command::new(&script)));
+  }
+  for line in config.hooks.get(&name).into_iter().flatten() {
+    let mut command = Command::new("sh");
+    command.arg("-c").arg(line);
+    hooks.push((line.clone(), command));
+  }
+
+  let (phase, operation) = name.split_once('-').unwrap_or_default();
+  let mut failures = vec![];
+  for (hook, mut command) in hooks {
+    command
+      .env("MAPHP_EVENT", &name)
+      .env("MAPHP_PHASE", phase)
+      .env("MAPHP_OPERATION", operation)
+      .env("MAPHP_VERSION", &*source.name())
+      .env("MAPHP_SRC", source.src())
+      .env("MAPHP_DIST", source.dist())
+      .env("MAPHP_WORK_DIR", source.work_dir())
+      .envs(extra.iter().map(|(key, value)| (key, value)))
+      .stdin(std::process::Stdio::null());
+
+    let error = match command.status().await {
+      Ok(status) if status.success() => continue,
+      Ok(status) => format!("failed ({status})"),
+      Err(err) => format!("couldn't run: {err}"),
+    };
+    if config.abort_on_hook_failure {
+      bail!("{name} hook `{hook}` {error}");
+    }
+    failures.push(format!("{name} hook `{hook}` {error}"));
+  }
+
+  Ok(failures)
+}
+
+#[test]
+fn test_event_name() {
+  assert_eq!(Event(Phase::Pre, Operation::Install).to_string(), "pre-install");
+  assert_eq!(Event(Phase::Post, Operation::Upgrade).to_string(), "post-upgrade");
+}
@@ -1,4 +1,6 @@
-use crate::Commands;
+use crate::manifest::BuildOptions;
+use crate::{Commands, Maybe, strip_php};
+use anyhow::{Context, anyhow};
 
 impl Commands {
   pub fn is_verbose(&self) -> bool {
@@ -12,4 +14,130 @@ impl Commands {
   pub fn is_force(&self) -> bool {
     matches!(*self, Self::Install { force: true, .. })
   }
+
+  pub fn is_build_from_source(&self) -> bool {
+    matches!(*self, Self::Install { build_from_source: true, .. })
+  }
+
+  /// Package given to `install --from-package`
+  pub fn from_package(&self) -> Option<&std::path::Path> {
+    match *self {
+      Self::Install { ref from_package, .. } => from_package.as_deref(),
+      _ => None,
+    }
+  }
+
+  /// Answer for "use it after install" given from `--use`/`--no-use`
+  pub fn use_choice(&self) -> Option<bool> {
+    match *self {
+      Self::Install { use_it: true, .. } => Some(true),
+      Self::Install { no_use: true, .. } => Some(false),
+      _ => None,
+    }
+  }
+
+  /// Tags given on the command line followed by the ones listed in `--from-file`, duplicates removed.
+  pub fn install_tags(&self) -> Maybe<Vec<String>> {
+    let Self::Install { tags, from_file, .. } = self else {
+      return Ok(vec![]);
+    };
+
+    let mut all = tags.clone();
+    if let Some(file) = from_file {
+      let content = std::fs::read_to_string(file).with_context(|| format!("Couldn't read {}", file.display()))?;
+      for line in content.lines().map(str::trim).filter(|l| !l.is_empty() && !l.starts_with('#')) {
+        all.push(strip_php(line).map_err(|e| anyhow!(e))?);
+      }
+    }
+
+    let mut seen = std::collections::HashSet::new();
+    all.retain(|tag| seen.insert(tag.clone()));
+    Ok(all)
+  }
+
+  /// Build options given to `maphp install`
+  pub fn build_options(&self) -> BuildOptions {
+    macro_rules! emit {
+      ($ident:ident, $a:ident $(,)?) => {
+        if $ident {
+          $a.push(::core::concat!("--", ::core::stringify!($ident)).replace("_", "-"));
+        }
+      };
+      (PathBuf($value:ident), $vec:ident $(,)?) => {
+        if $value.as_os_str() == "default" {
+          $vec.push(::core::concat!("--", ::core::stringify!($value)).replace("_", "-"));
+        } else if !$value.as_os_str().is_empty() {
+          $vec.push(::core::concat!("--", ::core::stringify!($value), "=").replace("_", "-") + $value.as_os_str().to_str().unwrap());
+        }
+      };
+    }
+
+    let Commands::Install {
+      enable_calendar,
+      enable_intl,
+      enable_mbstring,
+      enable_pcntl,
+      enable_bcmath,
+      enable_mysqlnd,
+      enable_fpm,
+      with_curl,
+      with_openssl,
+      with_pear,
+      with_zip,
+      with_zlib,
+      with_password_argon2,
+      ref with_mysqli,
+      ref with_pdo_mysqli,
+      ref with_pgsql,
+      ref with_pdo_pgsql,
+      ref configure_args,
+      debug,
+      ..
+    } = *self
+    else {
+      return BuildOptions::default();
+    };
+
+    let mut args = vec![];
+    emit!(enable_calendar, args);
+    emit!(enable_intl, args);
+    emit!(enable_mbstring, args);
+    emit!(enable_pcntl, args);
+    emit!(enable_bcmath, args);
+    emit!(enable_mysqlnd, args);
+    emit!(enable_fpm, args);
+    emit!(with_curl, args);
+    emit!(with_openssl, args);
+    emit!(with_pear, args);
+    emit!(with_zip, args);
+    emit!(with_zlib, args);
+    emit!(with_password_argon2, args);
+
+    emit!(PathBuf(with_mysqli), args);
+    emit!(PathBuf(with_pdo_mysqli), args);
+    emit!(PathBuf(with_pgsql), args);
+    emit!(PathBuf(with_pdo_pgsql), args);
+
+    args.extend(configure_args.iter().filter(|arg| !arg.is_empty()).cloned());
+    BuildOptions { configure_args: args, debug }
+  }
+}
+
+#[test]
+fn test_default_build_options() {
+  use clap::Parser;
+
+  let cli = crate::Cli::try_parse_from(["maphp", "install", "8.3.13"]).unwrap();
+  assert!(cli.command.build_options().is_default());
+}
+
+#[test]
+fn test_trailing_configure_args() {
+  use clap::Parser;
+
+  let cli = crate::Cli::try_parse_from(["maphp", "install", "8.3.13", "--configure-args", "--with-foo", "--debug", "8.2.0"]).unwrap();
+  let options = cli.command.build_options();
+  assert!(options.configure_args.ends_with(&["--with-foo".into(), "--debug".into(), "8.2.0".into()]));
+  assert!(!options.debug);
+  assert!(matches!(cli.command, crate::Commands::Install { ref tags, .. } if tags == &["8.3.13"]));
 }
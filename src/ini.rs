@@ -0,0 +1,152 @@
+use crate::Maybe;
+use crate::source::SourcePHP;
+use anyhow::ensure;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+
+/// Header of the snippets maphp manages
+const MANAGED: &str = "; Managed by maphp, change it with `maphp {}`";
+
+/// Directory of the ini snippets of a version, new builds scan it via `--with-config-file-scan-dir`
+pub fn conf_dir(source: &SourcePHP) -> PathBuf {
+  source.dist().join("lib/conf.d")
+}
+
+/// Ini snippet as ordered `key=value` lines, disabled lines are kept commented out
+#[derive(Debug, Default, PartialEq)]
+pub struct Snippet {
+  lines: Vec<(String, String, bool)>,
+}
+
+impl Snippet {
+  pub fn load(path: impl AsRef<Path>) -> Maybe<Self> {
+    match std::fs::read_to_string(path) {
+      Ok(text) => Ok(Self::parse(&text)),
+      Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+      Err(err) => Err(err.into()),
+    }
+  }
+
+  fn parse(text: &str) -> Self {
+    let lines = text
+      .lines()
+      .filter(|line| !line.starts_with("; "))
+      .filter_map(|line| {
+        let (enabled, line) = match line.strip_prefix(';') {
+          Some(line) => (false, line),
+          None => (true, line),
+        };
+        let (key, value) = line.split_once('=')?;
+        Some((key.trim().to_owned(), value.trim().to_owned(), enabled))
+      })
+      .collect();
+    Self { lines }
+  }
+
+  /// Value of an enabled line
+  pub fn get(&self, key: &str) -> Option<&str> {
+    self
+      .lines
+      .iter()
+      .find(|(k, _, enabled)| k == key && *enabled)
+      .map(|(_, value, _)| value.as_str())
+  }
+
+  /// Set and enable a line, appended when missing
+  pub fn set(&mut self, key: &str, value: impl Into<String>) {
+    let value = value.into();
+    match self.lines.iter_mut().find(|(k, ..)| k == key) {
+      Some(line) => *line = (key.to_owned(), value, true),
+      None => self.lines.push((key.to_owned(), value, true)),
+    }
+  }
+
+  /// Comment a line out, its value is kept for when it is enabled again
+  pub fn disable(&mut self, key: &str) {
+    if let Some(line) = self.lines.iter_mut().find(|(k, ..)| k == key) {
+      line.2 = false;
+    }
+  }
+
+  fn render(&self, command: &str) -> String {
+    let mut text = MANAGED.replace("{}", command);
+    text.push('\n');
+    for (key, value, enabled) in &self.lines {
+      let comment = if *enabled { "" } else { ";" };
+      text.push_str(&format!("{comment}{key}={value}\n"));
+    }
+    text
+  }
+
+  pub fn save(&self, path: impl AsRef<Path>, command: &str) -> Maybe<()> {
+    if let Some(parent) = path.as_ref().parent() {
+      std::fs::create_dir_all(parent)?;
+    }
+    crate::disk::atomic_write(path, self.render(command))
+  }
+}
+
+/// Directories the `php` binary scans for additional ini files, empty when built without one
+pub fn scan_dirs(source: &SourcePHP) -> Vec<PathBuf> {
+  let Ok(output) = std::process::Command::new(source.dist().join("bin/php"))
+    .arg("--ini")
+    .stdin(Stdio::null())
+    .output()
+  else {
+    return vec![];
+  };
+  let output = String::from_utf8_lossy(&output.stdout);
+  let Some((_, dirs)) = output
+    .lines()
+    .find_map(|line| line.strip_prefix("Scan for additional .ini files in"))
+    .and_then(|line| line.split_once(':'))
+  else {
+    return vec![];
+  };
+  match dirs.trim() {
+    "(none)" | "" => vec![],
+    dirs => std::env::split_paths(dirs).collect(),
+  }
+}
+
+/// Fail unless the `php` of `source` loads its snippets.
+///
+/// The scan dir is compiled in, so a version moved by `migrate` or installed from a package built
+/// elsewhere only loads them with `PHP_INI_SCAN_DIR`
+pub fn ensure_scanned(source: &SourcePHP) -> Maybe<()> {
+  let dir = conf_dir(source);
+  ensure!(
+    scan_dirs(source).contains(&dir),
+    "PHP {} doesn't scan {dir}, export PHP_INI_SCAN_DIR={dir} or reinstall it",
+    source.name(),
+    dir = dir.display()
+  );
+  Ok(())
+}
+
+/// Evaluate a PHP expression with the version's ini files, `None` when php fails
+pub fn eval(source: &SourcePHP, code: &str) -> Option<String> {
+  let output = std::process::Command::new(source.dist().join("bin/php"))
+    .arg("-r")
+    .arg(format!("echo {code};"))
+    .stdin(Stdio::null())
+    .output()
+    .ok()?;
+  output.status.success().then(|| String::from_utf8_lossy(&output.stdout).trim().to_owned())
+}
+
+#[test]
+fn test_snippet() {
+  let mut snippet = Snippet::parse("; Managed by maphp\nzend_extension=xdebug\n;xdebug.mode = coverage\n");
+  assert_eq!(snippet.get("zend_extension"), Some("xdebug"));
+  assert_eq!(snippet.get("xdebug.mode"), None);
+
+  snippet.set("xdebug.mode", "debug,develop");
+  snippet.disable("zend_extension");
+  snippet.set("xdebug.start_with_request", "trigger");
+  assert_eq!(
+    snippet.render("xdebug"),
+    "; Managed by maphp, change it with `maphp xdebug`\n;zend_extension=xdebug\nxdebug.mode=debug,develop\nxdebug.start_with_request=trigger\n"
+  );
+  assert_eq!(Snippet::parse(&snippet.render("xdebug")), snippet);
+}
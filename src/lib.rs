@@ -0,0 +1,37 @@
+//! Install, switch and remove PHP versions built from php-src.
+//!
+//! [`Manager`] is the entry point, the `maphp` binary is a thin layer over it:
+//!
+//! ```no_run
+//! # async fn run() -> maphp::Maybe<()> {
+//! use maphp::{Manager, Options};
+//!
+//! let manager = Manager::new("/opt/maphp", Options::default())?.with_progress(|event| println!("{event}"));
+//! let source = manager.install("8.3.12", &Default::default()).await?;
+//! manager.use_version(&source.name()).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+pub type Maybe<T, E = anyhow::Error> = Result<T, E>;
+
+pub mod alias;
+pub mod config;
+pub mod date;
+pub mod disk;
+pub mod downloader;
+pub mod fpm;
+pub mod github;
+pub mod hooks;
+pub mod ini;
+mod manager;
+pub mod manifest;
+pub mod package;
+pub mod progress;
+pub mod repository;
+pub mod source;
+pub mod static_const;
+pub mod stats;
+pub mod verify;
+
+pub use manager::{Manager, Options, ProgressEvent, Removal};
@@ -1,24 +1,48 @@
+use crate::actions::alias::{AliasArgs, UnaliasArgs};
+use crate::actions::composer::ComposerArgs;
+use crate::actions::current::CurrentArgs;
+use crate::actions::default::DefaultArgs;
+use crate::actions::diff::DiffArgs;
+use crate::actions::du::DuArgs;
+use crate::actions::fpm::FpmArgs;
+use crate::actions::info::InfoArgs;
 use crate::actions::list::ListArgs;
-use crate::downloader::Downloader;
+use crate::actions::migrate::MigrateArgs;
+use crate::actions::opcache::OpcacheArgs;
+use crate::actions::pack::PackArgs;
+use crate::actions::prune::PruneArgs;
+use crate::actions::remove::RemoveArgs;
+use crate::actions::serve::ServeArgs;
+use crate::actions::test::TestArgs;
+use crate::actions::upgrade::{OutdatedArgs, UpgradeArgs};
+use crate::actions::xdebug::XdebugArgs;
+use crate::progress::ProgressMode;
+use crate::repository::Repository;
 use crate::source::SourcePHP;
-use crate::static_const::{CLI, THEME};
+use crate::stats::Version;
+use crate::stats::releases::{Branch, ReleaseIndex, Support};
 use anyhow::{bail, ensure};
 use clap::{Parser, Subcommand};
 use colored::Colorize;
-use indicatif::{HumanBytes, ProgressState, ProgressStyle};
+use lazy_static::lazy_static;
+use maphp::{Manager, Options};
 use std::env::var;
 use std::ffi::{OsStr, OsString};
-use std::fs::create_dir_all;
+use std::io::IsTerminal;
 use std::path::{Path, PathBuf};
 
-type Maybe<T, E = anyhow::Error> = Result<T, E>;
+pub(crate) use maphp::{
+  Maybe, alias, config, date, disk, fpm, github, hooks, ini, manifest, package, progress, repository, source, static_const, stats, verify,
+};
 
 pub mod actions;
-mod downloader;
 mod imp;
-pub mod source;
-pub mod static_const;
-pub mod stats;
+pub mod output;
+
+lazy_static! {
+  pub static ref THEME: dialoguer::theme::ColorfulTheme = dialoguer::theme::ColorfulTheme::default();
+  pub static ref CLI: Cli = Cli::parse().setup().unwrap();
+}
 
 #[derive(Parser, Debug)]
 #[command(author, version, about = "A PHP CLI manager", long_about = None)]
@@ -30,9 +54,46 @@ pub struct Cli {
   /// Change default root managed directory
   #[arg(long, env, default_value = static_const::DEFAULT_WORK_DIR.as_os_str(), value_parser = parse_work_dir)]
   work_dir: PathBuf,
+
+  /// Base URL of php.net release metadata (`branches.php` and `index.php`)
+  #[arg(long, env = "MAPHP_INDEX_URL", global = true, default_value = static_const::RELEASES_URL, value_parser = parse_index_url)]
+  index_url: reqwest::Url,
+
+  /// Hours after which known tags and the release index are refreshed automatically, 0 never expires them
+  #[arg(long, env = "MAPHP_CACHE_TTL", global = true, default_value_t = 24, value_name = "HOURS")]
+  cache_ttl: u64,
+
+  /// Binary repository (URL or directory with an `index.json`), overrides `repository` of config.json
+  #[arg(long, env = "MAPHP_REPOSITORY", global = true, value_name = "URL|DIR")]
+  repository: Option<String>,
+
+  /// Answer every question with yes
+  #[arg(short = 'y', long, global = true, default_value_t = false)]
+  yes: bool,
+
+  /// Never prompt: questions needing a decision fail unless --yes is given, the others take their safe default.
+  /// Also the case when stdin is not a terminal
+  #[arg(long, global = true, default_value_t = false)]
+  no_input: bool,
+
+  /// How downloads and builds report progress on stderr
+  #[arg(long, env = "MAPHP_PROGRESS", global = true, value_enum, default_value_t = ProgressMode::Auto)]
+  progress: ProgressMode,
+
+  /// Library side of the CLI, set up from the arguments above
+  #[arg(skip)]
+  manager: Option<Manager>,
+}
+
+/// Directory maphp manages inside a work dir, `.maphp` is appended unless the path already ends with it
+pub(crate) fn managed_dir(work_dir: &Path) -> PathBuf {
+  match work_dir.ends_with(".maphp") {
+    true => work_dir.to_path_buf(),
+    false => work_dir.join(".maphp"),
+  }
 }
 
-fn parse_work_dir(value: &str) -> Result<PathBuf, String> {
+pub(crate) fn parse_work_dir(value: &str) -> Result<PathBuf, String> {
   if value == "~"
     && let Ok(home) = var("HOME")
   {
@@ -42,75 +103,232 @@ fn parse_work_dir(value: &str) -> Result<PathBuf, String> {
   }
 }
 
+pub(crate) fn parse_index_url(value: &str) -> Result<reqwest::Url, String> {
+  let value = if value.ends_with('/') { value.to_owned() } else { format!("{value}/") };
+  reqwest::Url::parse(&value).map_err(|err| err.to_string())
+}
+
 impl Cli {
   pub fn setup(mut self) -> Maybe<Self> {
-    if !self.work_dir.ends_with(".maphp") {
-      self.work_dir = self.work_dir.join(".maphp");
+    let options = Options {
+      force: self.command.is_force(),
+      build_from_source: self.command.is_build_from_source(),
+      verbose: self.command.is_verbose(),
+      repository: self.repository.clone(),
+      progress: self.progress,
+    };
+    let mode = self.progress;
+    let manager = Manager::new(managed_dir(&self.work_dir), options)?.with_progress(move |event| output::print_event(mode, event));
+    self.work_dir = manager.work_dir().to_path_buf();
+    self.manager = Some(manager);
+    Ok(self)
+  }
+
+  pub fn manager(&self) -> &Manager {
+    self.manager.as_ref().expect("Cli::setup creates the manager")
+  }
+
+  pub fn work_dir(&self) -> &Path {
+    &self.work_dir
+  }
+
+  pub fn tags_file(&self) -> PathBuf {
+    self.manager().tags_file()
+  }
+
+  /// Age after which the tag cache is refreshed automatically
+  pub fn cache_ttl(&self) -> std::time::Duration {
+    std::time::Duration::from_secs(self.cache_ttl * 3600)
+  }
+
+  pub fn releases_file(&self) -> PathBuf {
+    self.manager().releases_file()
+  }
+
+  pub fn index_url(&self) -> &reqwest::Url {
+    &self.index_url
+  }
+
+  /// Release index from the local cache, fetched when missing. Errors are reported but not fatal.
+  pub async fn release_index(&self) -> Option<ReleaseIndex> {
+    let cached = ReleaseIndex::load(self.releases_file());
+    if cached.as_ref().is_some_and(|index| !index.is_stale(self.cache_ttl())) {
+      return cached;
     }
 
-    self.work_dir = match dunce::canonicalize(&self.work_dir) {
-      Ok(dir) => dir,
-      Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
-        create_dir_all(&self.work_dir)?;
-        dunce::canonicalize(self.work_dir)?
+    match ReleaseIndex::fetch(&self.index_url).await {
+      Ok(index) => {
+        if let Err(err) = index.save(self.releases_file()) {
+          eprintln!("⚠️ Couldn't save release index: {err}");
+        }
+        Some(index)
       }
-      Err(err) => return Err(err.into()),
-    };
+      Err(err) if cached.is_some() => {
+        eprintln!("⚠️ Couldn't refresh release index, using the cached one: {err:#}");
+        cached
+      }
+      Err(err) => {
+        eprintln!("⚠️ Couldn't fetch release index: {err:#}");
+        None
+      }
+    }
+  }
 
-    create_dir_all(self.archives())?;
-    Ok(self)
+  pub fn config_file(&self) -> PathBuf {
+    self.manager().config_file()
   }
 
-  pub fn tags_file(&self) -> PathBuf {
-    self.work_dir.join("tags.json")
+  /// Binary repository from `--repository` or the config, if any
+  pub fn repository(&self) -> Maybe<Option<Repository>> {
+    self.manager().repository()
+  }
+
+  pub fn aliases_file(&self) -> PathBuf {
+    self.manager().aliases_file()
+  }
+
+  /// Source directory of an installed version, `version` may also be an alias
+  pub fn resolve(&self, version: &str) -> Maybe<PathBuf> {
+    Ok(self.manager().resolve(version)?.src().to_path_buf())
+  }
+
+  /// Installed `version` (or alias), the active version when omitted
+  pub fn target(&self, version: Option<&str>) -> Maybe<SourcePHP> {
+    let source = match version {
+      Some(version) => self.manager().resolve(version)?,
+      None => match self.manager().active()? {
+        Some(source) => source,
+        None => bail!("No active version, pass the version to use"),
+      },
+    };
+
+    ensure!(source.is_installed(), "Version `{}` is not installed", source.name());
+    Ok(source)
   }
 
   pub fn bin(&self) -> PathBuf {
-    self.work_dir.join("bin")
+    self.manager().bin()
   }
 
   pub fn archives(&self) -> PathBuf {
-    self.work_dir.join("archives")
+    self.manager().archives()
   }
 
   pub async fn run(&self) -> Maybe<()> {
     match self.command {
-      Commands::Install { ref tag, .. } => self.install(tag).await,
-      Commands::Remove { ref tag } => self.remove(tag.as_deref()),
+      Commands::Install { .. } => self.install().await,
+      Commands::Remove(ref args) => args.handle().await,
+      Commands::Prune(ref args) => args.handle().await,
+      Commands::Du(ref args) => args.handle().await,
+      Commands::Pack(ref args) => args.handle().await,
+      Commands::Migrate(ref args) => args.handle().await,
+      Commands::Composer(ref args) => args.handle().await,
+      Commands::Fpm(ref args) => args.handle().await,
+      Commands::Serve(ref args) => args.handle().await,
+      Commands::Xdebug(ref args) => args.handle().await,
+      Commands::Opcache(ref args) => args.handle().await,
+      Commands::Test(ref args) => args.handle().await,
+      Commands::Diff(ref args) => args.handle().await,
       Commands::List(ref args) => args.handle().await,
       Commands::Use { ref tag } => self.r#use(tag.as_deref()).await,
+      Commands::Info(ref args) => args.handle().await,
+      Commands::Current(ref args) => args.handle().await,
+      Commands::Outdated(ref args) => args.handle().await,
+      Commands::Alias(ref args) => args.handle().await,
+      Commands::Default(ref args) => args.handle().await,
+      Commands::Unalias(ref args) => args.handle().await,
+      Commands::Upgrade(ref args) => args.handle().await,
     }
   }
 
-  async fn install(&self, tag: &str) -> Maybe<()> {
-    let src = self.archives().join(tag);
+  async fn install(&self) -> Maybe<()> {
+    if let Some(package) = self.command.from_package() {
+      let source = self.manager().install_package(package).await?;
+      println!("Installed {} from {}", source.name(), package.display());
+      return self.finish_install(&source).await;
+    }
+
+    let tags = self.command.install_tags()?;
+    ensure!(!tags.is_empty(), "No version to install");
+    ensure!(
+      tags.len() == 1 || self.command.use_choice() != Some(true),
+      "--use can't be combined with multiple versions"
+    );
+    self.warn_unsupported(&tags).await?;
+
+    let options = self.command.build_options();
+    if let [tag] = tags.as_slice() {
+      let source = self.manager().install(tag, &options).await?;
+      return self.finish_install(&source).await;
+    }
 
-    if self.command.is_force() || !src.join("buildconf").is_file() {
-      let mut downloader = Downloader::new(tag).await?;
-      downloader.start().await?;
-      if self.command.is_force() {
-        _ = std::fs::remove_dir_all(&src);
-        _ = std::fs::remove_file(&src);
+    let results = self.manager().install_many(&tags, &options).await?;
+    println!("Installation summary:");
+    let mut failed = 0;
+    for (tag, result) in results {
+      match result {
+        Ok(_) => println!("  {} {tag}", "✅".green()),
+        Err(err) => {
+          failed += 1;
+          println!("  {} {tag}: {}", "❌".red(), format!("{err:#}").red());
+        }
       }
-      downloader.extract(tag, self.command.is_verbose())?;
     }
 
-    let source = SourcePHP::new(&src);
-    if self.command.is_force() || !source.is_installed() {
-      let compilation = source.install().await;
-      if compilation.is_err() {
-        _ = std::fs::remove_dir_all(src.join("dist"));
-        compilation?;
+    ensure!(failed == 0, "{failed} of {} installation(s) failed", tags.len());
+    Ok(())
+  }
+
+  /// Warn about versions whose series reached end of life and ask whether to continue
+  async fn warn_unsupported(&self, tags: &[String]) -> Maybe<()> {
+    let Some(index) = self.release_index().await else { return Ok(()) };
+
+    for tag in tags {
+      let Ok(version) = tag.parse::<Version>() else { continue };
+      if index.support(&version) != Some(Support::Eol) {
+        continue;
+      }
+
+      let since = match index.branch(&version).and_then(Branch::eol_date) {
+        Some(date) => format!(" on {date}"),
+        None => String::new(),
+      };
+      println!(
+        "{}",
+        format!(
+          "⚠️ PHP {}.{} reached end of life{since} and no longer receives security fixes",
+          version.major, version.minor
+        )
+        .yellow()
+      );
+
+      match self.confirm(format!("Install {tag} anyway?"), Some(false))? {
+        Some(true) => {}
+        Some(false) => bail!("Operation canceled"),
+        None => bail!("Refusing to install an end of life version without confirmation, pass --yes"),
       }
     }
 
-    source.setup_ini().await?;
+    Ok(())
+  }
+
+  async fn finish_install(&self, source: &SourcePHP) -> Maybe<()> {
+    let use_it = match self.command.use_choice() {
+      Some(choice) => choice,
+      None => match self.confirm("✅ Installation completed, Use it?", None)? {
+        Some(answer) => answer,
+        None => {
+          println!(
+            "✅ Installation completed, run `maphp use {}` or pass --use to activate it",
+            source.name()
+          );
+          false
+        }
+      },
+    };
 
-    if dialoguer::Confirm::with_theme(&*THEME)
-      .with_prompt("✅ Installation completed, Use it?")
-      .interact()?
-    {
-      source.link().await?;
+    if use_it {
+      self.manager().link(source).await?;
       println!("Sucess!");
 
       if !self.path_registered() {
@@ -132,16 +350,49 @@ impl Cli {
   }
 
   async fn r#use(&self, tag: Option<&str>) -> Maybe<()> {
-    let src = match tag {
-      None => self.select("Choose installed version you want to use")?,
-      Some(t) => self.archives().join(t),
-    };
+    match tag {
+      None => {
+        self
+          .manager()
+          .link(&SourcePHP::new(self.select("Choose installed version you want to use")?))
+          .await
+      }
+      Some(t) => self.manager().use_version(t).await.map(drop),
+    }
+  }
 
-    SourcePHP::new(src).link().await?;
-    Ok(())
+  /// Whether questions can be asked on stdin
+  pub fn is_interactive(&self) -> bool {
+    !self.yes && !self.no_input && std::io::stdin().is_terminal()
   }
 
-  fn select(&self, prompt: impl AsRef<str>) -> Maybe<PathBuf> {
+  /// Ask a yes/no question.
+  ///
+  /// `--yes` answers it right away, `None` is returned when prompting isn't possible.
+  pub(crate) fn confirm(&self, prompt: impl Into<String>, default: Option<bool>) -> Maybe<Option<bool>> {
+    if self.yes {
+      return Ok(Some(true));
+    }
+
+    if !self.is_interactive() {
+      return Ok(None);
+    }
+
+    let mut confirm = dialoguer::Confirm::with_theme(&*THEME).with_prompt(prompt);
+    if let Some(default) = default {
+      confirm = confirm.default(default);
+    }
+
+    Ok(Some(confirm.interact()?))
+  }
+
+  pub(crate) fn select(&self, prompt: impl AsRef<str>) -> Maybe<PathBuf> {
+    ensure!(
+      self.is_interactive(),
+      "{}: no version given and prompts are disabled, pass the version explicitly",
+      prompt.as_ref()
+    );
+
     let archives = std::fs::read_dir(self.archives())?
       .flatten()
       .filter_map(|dir| dir.path().is_dir().then_some(dir.file_name()))
@@ -156,53 +407,32 @@ impl Cli {
 
     Ok(self.archives().join(&archives[pos]))
   }
-
-  fn remove(&self, tag: Option<&str>) -> Maybe<()> {
-    let src = match tag {
-      Some(t) => self.archives().join(t),
-      None => self.select("Choose installed version you want to remove")?,
-    };
-
-    let source = SourcePHP::new(&src);
-    let true = dialoguer::Confirm::with_theme(&*THEME)
-      .with_prompt(format!("Are you sure want to remove ({})?", source.details().red()))
-      .default(false)
-      .interact()
-      .unwrap_or(false)
-    else {
-      bail!("Operation canceled")
-    };
-
-    if source.is_installed() {
-      std::fs::remove_dir_all(src)?;
-      if source.is_in_path() {
-        std::fs::remove_dir_all(self.bin()).ok();
-      }
-
-      println!("PHP {} successfully deleted", source.name());
-      return Ok(());
-    }
-
-    bail!("No matching version `{}` as found", source.name())
-  }
 }
 
 fn strip_php(value: &str) -> Result<String, String> {
-  if &value[..4] == "php-" {
-    Ok(value[4..].to_owned())
-  } else {
-    Ok(value.to_owned())
-  }
+  Ok(value.strip_prefix("php-").unwrap_or(value).to_owned())
 }
 
 #[derive(Subcommand, Debug)]
 pub enum Commands {
-  /// Install PHP Version
+  /// Install one or more PHP Versions
   Install {
-    /// PHP Tag SemVer
+    /// PHP Tag SemVer, multiple versions are downloaded and built in parallel
     #[command()]
-    #[arg(value_parser = strip_php)]
-    tag: String,
+    #[arg(value_parser = strip_php, required_unless_present_any = ["from_file", "from_package"])]
+    tags: Vec<String>,
+
+    /// Read versions to install from a file, one per line
+    #[arg(long, value_name = "FILE")]
+    from_file: Option<PathBuf>,
+
+    /// Always compile, even when the binary repository has a matching package
+    #[arg(long, default_value_t = false)]
+    build_from_source: bool,
+
+    /// Install a build made by `maphp pack` instead of compiling, `--force` replaces an existing version
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["tags", "from_file"])]
+    from_package: Option<PathBuf>,
 
     /// Enable calendar extension
     #[arg(long, default_value_t = true)]
@@ -228,6 +458,10 @@ pub enum Commands {
     #[arg(long, default_value_t = false)]
     enable_mysqlnd: bool,
 
+    /// Build php-fpm, managed with `maphp fpm`
+    #[arg(long, default_value_t = false)]
+    enable_fpm: bool,
+
     /// Enable curl extension
     #[arg(long, default_value_t = true)]
     with_curl: bool,
@@ -312,17 +546,55 @@ pub enum Commands {
     #[arg(long, default_value_t = false)]
     force: bool,
 
-    /// Pass additional args to configure
-    #[arg(long, trailing_var_arg = true, num_args = 0.., allow_hyphen_values = true, allow_negative_numbers = true)]
+    /// Use the installed version without asking
+    #[arg(long = "use", default_value_t = false, conflicts_with = "no_use")]
+    use_it: bool,
+
+    /// Don't use the installed version and don't ask
+    #[arg(long, default_value_t = false)]
+    no_use: bool,
+
+    /// Pass additional args to configure, everything after it is passed as is
+    // Trailing on its own: `trailing_var_arg` only applies to positionals and clap asserts on it next to `tags`
+    #[arg(long, num_args = 0.., allow_hyphen_values = true, allow_negative_numbers = true)]
     configure_args: Vec<String>,
   },
 
   /// Removes installed PHP Version
-  Remove {
-    #[command()]
-    #[arg(default_value = None)]
-    tag: Option<String>,
-  },
+  Remove(RemoveArgs),
+
+  /// Remove partial sources of failed builds and references to removed versions
+  Prune(PruneArgs),
+
+  /// Show disk usage of every version split by kind of file
+  Du(DuArgs),
+
+  /// Pack an installed version into a relocatable tarball
+  Pack(PackArgs),
+
+  /// Move the work dir elsewhere and relocate every installed version
+  Migrate(MigrateArgs),
+
+  /// Manage Composer of installed versions
+  Composer(ComposerArgs),
+
+  /// Manage php-fpm of versions built with --enable-fpm
+  Fpm(FpmArgs),
+
+  /// Serve a directory with the built-in web server of a version, restarted when its ini or .php-version changes
+  Serve(ServeArgs),
+
+  /// Turn Xdebug on or off and set its mode, through conf.d/xdebug.ini of a version
+  Xdebug(XdebugArgs),
+
+  /// Turn OPcache on or off and set the JIT mode, through conf.d/opcache.ini of a version
+  Opcache(OpcacheArgs),
+
+  /// Run php-src's run-tests.php against an installed build
+  Test(TestArgs),
+
+  /// Compare extensions, configure options, ini values and build manifests of two installed versions
+  Diff(DiffArgs),
   /// Lists all PHP version
   List(ListArgs),
 
@@ -331,22 +603,33 @@ pub enum Commands {
     #[arg(default_value = None)]
     tag: Option<String>,
   },
+
+  /// Show details of an installed PHP version
+  Info(InfoArgs),
+
+  /// Show the active PHP version
+  Current(CurrentArgs),
+
+  /// List installed versions with a newer patch release
+  Outdated(OutdatedArgs),
+
+  /// Install the newest patch of installed series with the same build options
+  Upgrade(UpgradeArgs),
+
+  /// Name an installed version, e.g. `maphp alias prod 8.2.20`
+  Alias(AliasArgs),
+
+  /// Remove an alias
+  Unalias(UnaliasArgs),
+
+  /// Show or set the version used when the active one is removed
+  Default(DefaultArgs),
 }
 
 fn triple_drip(value: &str) -> Result<PathBuf, String> {
   if value.is_empty() { Ok(PathBuf::new()) } else { Ok(value.into()) }
 }
 
-fn dl_template() -> ProgressStyle {
-  ProgressStyle::with_template("{spinner:.green} [{bar:30.cyan/blue}] {bytes} | {speed}")
-    .unwrap()
-    .with_key("speed", |s: &ProgressState, w: &mut dyn std::fmt::Write| {
-      let persec = s.per_sec().abs() as u64;
-      write!(w, "{}/s", HumanBytes(persec)).unwrap()
-    })
-    .progress_chars("#>-")
-}
-
 #[tokio::main]
 async fn main() -> Maybe<()> {
   CLI.run().await
@@ -0,0 +1,583 @@
+use crate::Maybe;
+use crate::alias::Aliases;
+use crate::config::Config;
+use crate::downloader::Downloader;
+use crate::fpm::Fpm;
+use crate::hooks::{self, Event, Operation, Phase};
+use crate::manifest::BuildOptions;
+use crate::progress::{ProgressMode, Reporter};
+use crate::repository::Repository;
+use crate::source::{SourcePHP, VersionInfo};
+use anyhow::{bail, ensure};
+use serde::Serialize;
+use std::fmt::{Debug, Display, Formatter};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// How versions are installed, the library counterpart of the `maphp install` flags
+#[derive(Debug, Clone, Default)]
+pub struct Options {
+  /// Rebuild or reinstall versions that are already installed
+  pub force: bool,
+  /// Always compile, even when the binary repository has a matching package
+  pub build_from_source: bool,
+  /// Print the whole build output instead of its last line
+  pub verbose: bool,
+  /// Binary repository (URL or directory), overrides `repository` of config.json
+  pub repository: Option<String>,
+  /// How downloads and builds are reported on stderr
+  pub progress: ProgressMode,
+}
+
+/// Step of an operation, reported to the callback given to [`Manager::with_progress`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum ProgressEvent {
+  /// Installing a package of the binary repository
+  Prebuilt {
+    version: String,
+  },
+  Downloading {
+    version: String,
+  },
+  Extracting {
+    version: String,
+  },
+  Compiling {
+    version: String,
+    jobs: usize,
+  },
+  Verifying {
+    version: String,
+  },
+  Verified {
+    version: String,
+  },
+  Installed {
+    version: String,
+    path: PathBuf,
+  },
+  Failed {
+    version: String,
+    error: String,
+  },
+  Linked {
+    version: String,
+  },
+  Removed {
+    version: String,
+  },
+  /// Something went wrong without stopping the operation, e.g. a failing hook
+  Warning {
+    version: String,
+    message: String,
+  },
+}
+
+impl Display for ProgressEvent {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Self::Prebuilt { version } => write!(f, "{version}: installing prebuilt package"),
+      Self::Downloading { version } => write!(f, "{version}: downloading"),
+      Self::Extracting { version } => write!(f, "{version}: extracting"),
+      Self::Compiling { version, jobs } => write!(f, "{version}: compiling with {jobs} job(s)"),
+      Self::Verifying { version } => write!(f, "{version}: verifying"),
+      Self::Verified { version } => write!(f, "{version}: verified"),
+      Self::Installed { version, path } => write!(f, "{version}: installed in {}", path.display()),
+      Self::Failed { version, error } => write!(f, "{version}: failed, {error}"),
+      Self::Linked { version } => write!(f, "{version}: linked"),
+      Self::Removed { version } => write!(f, "{version}: removed"),
+      Self::Warning { version, message } => write!(f, "{version}: {message}"),
+    }
+  }
+}
+
+/// What [`Manager::remove`] did besides deleting the source trees
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Removal {
+  pub removed: Vec<String>,
+  /// Versions whose php-fpm was running
+  pub stopped_fpm: Vec<String>,
+  /// Removed version that was the default one
+  pub default_cleared: Option<String>,
+  pub aliases_removed: Vec<String>,
+  /// The active version was removed
+  pub was_active: bool,
+  /// Default version linked in place of the removed active one
+  pub fallback: Option<String>,
+}
+
+type Callback = Arc<dyn Fn(&ProgressEvent) + Send + Sync>;
+
+/// PHP versions of one work dir: `archives/<version>` sources, the `bin` link and the json files next to them
+#[derive(Clone)]
+pub struct Manager {
+  work_dir: PathBuf,
+  options: Options,
+  progress: Option<Callback>,
+}
+
+impl Debug for Manager {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("Manager")
+      .field("work_dir", &self.work_dir)
+      .field("options", &self.options)
+      .finish_non_exhaustive()
+  }
+}
+
+impl Manager {
+  /// Manage `work_dir`, it's created when missing
+  pub fn new(work_dir: impl AsRef<Path>, options: Options) -> Maybe<Self> {
+    std::fs::create_dir_all(work_dir.as_ref().join("archives"))?;
+    Ok(Self {
+      work_dir: dunce::canonicalize(work_dir)?,
+      options,
+      progress: None,
+    })
+  }
+
+  /// Call `callback` on every step of an install, use or remove
+  pub fn with_progress(mut self, callback: impl Fn(&ProgressEvent) + Send + Sync + 'static) -> Self {
+    self.progress = Some(Arc::new(callback));
+    self
+  }
+
+  fn emit(&self, event: ProgressEvent) {
+    if let Some(ref progress) = self.progress {
+      progress(&event);
+    }
+  }
+
+  fn warn(&self, source: &SourcePHP, message: String) {
+    self.emit(ProgressEvent::Warning {
+      version: source.name().into_owned(),
+      message,
+    });
+  }
+
+  /// Run the hooks of `event`, failures that don't abort are reported as warnings
+  async fn hooks(&self, event: Event, source: &SourcePHP) -> Maybe<()> {
+    for failure in hooks::run(event, source, &[]).await? {
+      self.warn(source, failure);
+    }
+    Ok(())
+  }
+
+  pub fn options(&self) -> &Options {
+    &self.options
+  }
+
+  pub fn work_dir(&self) -> &Path {
+    &self.work_dir
+  }
+
+  pub fn archives(&self) -> PathBuf {
+    self.work_dir.join("archives")
+  }
+
+  /// Link to the `dist/bin` of the active version
+  pub fn bin(&self) -> PathBuf {
+    self.work_dir.join("bin")
+  }
+
+  pub fn config_file(&self) -> PathBuf {
+    Config::path(&self.work_dir)
+  }
+
+  pub fn aliases_file(&self) -> PathBuf {
+    Aliases::path(&self.work_dir)
+  }
+
+  pub fn tags_file(&self) -> PathBuf {
+    self.work_dir.join("tags.json")
+  }
+
+  pub fn releases_file(&self) -> PathBuf {
+    self.work_dir.join("releases.json")
+  }
+
+  /// Binary repository from the options or the config, if any
+  pub fn repository(&self) -> Maybe<Option<Repository>> {
+    let repository = match self.options.repository {
+      Some(ref repository) => Some(repository.clone()),
+      None => Config::load(self.config_file())?.repository,
+    };
+
+    repository.filter(|r| !r.is_empty()).map(|r| Repository::parse(&r)).transpose()
+  }
+
+  /// Source of `version`, which may also be an alias. It isn't necessarily installed
+  pub fn resolve(&self, version: &str) -> Maybe<SourcePHP> {
+    let src = self.version_dir(version)?;
+    if src.exists() {
+      return Ok(SourcePHP::new(src));
+    }
+
+    match Aliases::load(self.aliases_file())?.get(version) {
+      Some(target) => Ok(SourcePHP::new(self.version_dir(target)?)),
+      None => Ok(SourcePHP::new(src)),
+    }
+  }
+
+  /// `archives/<version>`, `version` must be a plain name so nothing outside of `archives` is reached
+  fn version_dir(&self, version: &str) -> Maybe<PathBuf> {
+    match crate::disk::child(&self.archives(), version) {
+      Some(dir) => Ok(dir),
+      None => bail!("Invalid version `{version}`"),
+    }
+  }
+
+  /// Installed versions, oldest first
+  pub fn installed(&self) -> Maybe<Vec<SourcePHP>> {
+    let mut sources = SourcePHP::scan_local(self.archives())?;
+    sources.sort_by_key(SourcePHP::version);
+    Ok(sources)
+  }
+
+  /// Installed version the `bin` link points at
+  pub fn active(&self) -> Maybe<Option<SourcePHP>> {
+    Ok(SourcePHP::scan_local(self.archives())?.into_iter().find(SourcePHP::is_in_path))
+  }
+
+  /// [`VersionInfo`] of every installed version, oldest first
+  pub fn list(&self) -> Maybe<Vec<VersionInfo>> {
+    Ok(self.installed()?.iter().map(SourcePHP::info).collect())
+  }
+
+  /// Install `tag` with its hooks, from the binary repository or compiled, then set up its php.ini and verify it
+  pub async fn install(&self, tag: &str, options: &BuildOptions) -> Maybe<SourcePHP> {
+    self
+      .hooks(Event(Phase::Pre, Operation::Install), &SourcePHP::new(self.archives().join(tag)))
+      .await?;
+    let source = self.build(tag, options, None).await?;
+    self.complete(&source, options).await?;
+    Ok(source)
+  }
+
+  /// Install every tag, downloaded and built concurrently with the available CPUs split between the builds.
+  ///
+  /// Pre-install hooks of all tags run first, a failing one stops everything.
+  pub async fn install_many(&self, tags: &[String], options: &BuildOptions) -> Maybe<Vec<(String, Maybe<SourcePHP>)>> {
+    for tag in tags {
+      self
+        .hooks(Event(Phase::Pre, Operation::Install), &SourcePHP::new(self.archives().join(tag)))
+        .await?;
+    }
+
+    let jobs = (num_cpus::get() / tags.len().max(1)).max(1);
+    let builds = tags.iter().map(|tag| {
+      let progress = Reporter::spinner(self.options.progress);
+      progress.set_prefix(format!("{tag}: waiting"));
+
+      async move {
+        let result = self.build(tag, options, Some((progress.clone(), jobs))).await;
+        match result {
+          Ok(ref source) => {
+            progress.set_prefix(format!("{tag}: done"));
+            progress.finish_with_message(source.src().display().to_string());
+          }
+          Err(ref err) => {
+            progress.set_prefix(format!("{tag}: failed"));
+            progress.abandon_with_message(err.to_string());
+          }
+        }
+        (tag.clone(), result)
+      }
+    });
+
+    let mut results = vec![];
+    for (tag, result) in futures_util::future::join_all(builds).await {
+      let result = match result {
+        Ok(source) => self.complete(&source, options).await.map(|_| source),
+        Err(err) => Err(err),
+      };
+      results.push((tag, result));
+    }
+
+    Ok(results)
+  }
+
+  /// Install a package made by `maphp pack`, replacing an existing version only with `force`
+  pub async fn install_package(&self, path: &Path) -> Maybe<SourcePHP> {
+    let (path, archives, force) = (path.to_path_buf(), self.archives(), self.options.force);
+    let source = tokio::task::spawn_blocking(move || crate::package::unpack(&path, &archives, None, force)).await??;
+    self.emit(ProgressEvent::Verifying {
+      version: source.name().into_owned(),
+    });
+    crate::verify::verify(&source, &source.build_options().unwrap_or_default())?;
+    self.emit(ProgressEvent::Verified {
+      version: source.name().into_owned(),
+    });
+    self.hooks(Event(Phase::Post, Operation::Install), &source).await?;
+    self.emit(ProgressEvent::Installed {
+      version: source.name().into_owned(),
+      path: source.src().to_path_buf(),
+    });
+    Ok(source)
+  }
+
+  /// php.ini, verification and post-install hooks of a fresh build
+  async fn complete(&self, source: &SourcePHP, options: &BuildOptions) -> Maybe<()> {
+    let version = source.name().into_owned();
+    let result = async {
+      if let Some(warning) = source.setup_ini(options.debug).await? {
+        self.warn(source, warning);
+      }
+      self.emit(ProgressEvent::Verifying { version: version.clone() });
+      crate::verify::verify(source, options)?;
+      self.emit(ProgressEvent::Verified { version: version.clone() });
+      self.hooks(Event(Phase::Post, Operation::Install), source).await
+    }
+    .await;
+
+    match result {
+      Ok(()) => self.emit(ProgressEvent::Installed {
+        version,
+        path: source.src().to_path_buf(),
+      }),
+      Err(ref err) => self.emit(ProgressEvent::Failed {
+        version,
+        error: format!("{err:#}"),
+      }),
+    }
+    result
+  }
+
+  /// Download (when needed) and compile a single tag, without hooks, php.ini or verification.
+  ///
+  /// When a shared progress bar and job count are given, every step is reported on that bar.
+  pub async fn build(&self, tag: &str, options: &BuildOptions, shared: Option<(Reporter, usize)>) -> Maybe<SourcePHP> {
+    let result = self.try_build(tag, options, shared).await;
+    if let Err(ref err) = result {
+      self.emit(ProgressEvent::Failed {
+        version: tag.to_owned(),
+        error: format!("{err:#}"),
+      });
+    }
+    result
+  }
+
+  async fn try_build(&self, tag: &str, options: &BuildOptions, shared: Option<(Reporter, usize)>) -> Maybe<SourcePHP> {
+    let src = self.archives().join(tag);
+    let force = self.options.force;
+
+    if let Some(source) = self.install_prebuilt(tag, options, shared.as_ref()).await? {
+      return Ok(source);
+    }
+
+    let downloader = match force || !src.join("buildconf").is_file() {
+      true => {
+        self.emit(ProgressEvent::Downloading { version: tag.to_owned() });
+        let mut downloader = match shared {
+          Some((ref progress, _)) => {
+            progress.set_prefix(format!("{tag}: downloading"));
+            Downloader::with_progress(tag, progress.clone()).await?
+          }
+          None => Downloader::new(tag, self.options.progress).await?,
+        };
+        downloader.start().await?;
+        if force {
+          _ = std::fs::remove_dir_all(&src);
+          _ = std::fs::remove_file(&src);
+        }
+        Some(downloader)
+      }
+      false => None,
+    };
+
+    // Keeps `prune` away from the sources until the build is over
+    let _lock = SourcePHP::new(&src).lock()?;
+    if let Some(mut downloader) = downloader {
+      self.emit(ProgressEvent::Extracting { version: tag.to_owned() });
+      let (tag, src, verbose) = (tag.to_owned(), src.clone(), self.options.verbose);
+      tokio::task::spawn_blocking(move || downloader.extract(&tag, src, verbose)).await??;
+    }
+
+    let (source, jobs) = match shared {
+      Some((progress, jobs)) => (SourcePHP::new(&src).with_progress(progress).with_jobs(jobs), jobs),
+      None => (SourcePHP::new(&src), num_cpus::get()),
+    };
+    let source = source.with_verbose(self.options.verbose).with_mode(self.options.progress);
+
+    if force || !source.is_installed() {
+      self.emit(ProgressEvent::Compiling {
+        version: tag.to_owned(),
+        jobs,
+      });
+      let compilation = source.install(options).await;
+      if compilation.is_err() {
+        _ = std::fs::remove_dir_all(src.join("dist"));
+        compilation?;
+      }
+    }
+
+    Ok(source)
+  }
+
+  /// Install `tag` from the binary repository unless it's installed or `build_from_source` is set
+  async fn install_prebuilt(&self, tag: &str, options: &BuildOptions, shared: Option<&(Reporter, usize)>) -> Maybe<Option<SourcePHP>> {
+    let installed = SourcePHP::new(self.archives().join(tag)).is_installed();
+    if self.options.build_from_source || (installed && !self.options.force) {
+      return Ok(None);
+    }
+    let Some(repository) = self.repository()? else { return Ok(None) };
+
+    self.emit(ProgressEvent::Prebuilt { version: tag.to_owned() });
+    let progress = match shared {
+      Some((progress, _)) => progress.clone(),
+      None => Reporter::spinner(self.options.progress),
+    };
+    let source = repository.install(&self.archives(), tag, options, &progress).await?;
+    if shared.is_none() {
+      match source {
+        Some(ref source) => progress.finish_with_message(format!("Installed prebuilt {}", source.name())),
+        None => progress.finish_and_clear(),
+      }
+    }
+
+    Ok(source)
+  }
+
+  /// Make `version` (or alias) the active one, with its use hooks
+  pub async fn use_version(&self, version: &str) -> Maybe<SourcePHP> {
+    let source = self.resolve(version)?;
+    self.link(&source).await?;
+    Ok(source)
+  }
+
+  /// Point the `bin` link at `source`, with its use hooks. Every activation goes through here
+  pub async fn link(&self, source: &SourcePHP) -> Maybe<()> {
+    ensure!(source.is_installed(), "Version `{}` is not installed", source.name());
+    self.hooks(Event(Phase::Pre, Operation::Use), source).await?;
+    source.link().await?;
+    self.hooks(Event(Phase::Post, Operation::Use), source).await?;
+    self.emit(ProgressEvent::Linked {
+      version: source.name().into_owned(),
+    });
+    Ok(())
+  }
+
+  /// Delete the source trees then clear the default and aliases pointing at them.
+  ///
+  /// When the active version is among them, the default version is linked instead.
+  pub async fn remove(&self, targets: &[SourcePHP]) -> Maybe<Removal> {
+    let mut config = Config::load(self.config_file())?;
+    let mut aliases = Aliases::load(self.aliases_file())?;
+    let mut removal = Removal::default();
+
+    let archives = self.archives();
+    if let Some(outside) = targets
+      .iter()
+      .find(|source| source.src().file_name().is_none() || source.src().parent() != Some(&archives))
+    {
+      bail!("{} isn't a version of {}", outside.src().display(), archives.display());
+    }
+
+    for source in targets {
+      let name = source.name().into_owned();
+      self.hooks(Event(Phase::Pre, Operation::Remove), source).await?;
+      removal.was_active |= source.is_installed() && source.is_in_path();
+      let src = source.src().to_path_buf();
+      if tokio::task::spawn_blocking(move || Fpm::new(&SourcePHP::new(src)).stop()).await?? {
+        removal.stopped_fpm.push(name.clone());
+      }
+      tokio::fs::remove_dir_all(source.src()).await?;
+      self.emit(ProgressEvent::Removed { version: name.clone() });
+      self.hooks(Event(Phase::Post, Operation::Remove), source).await?;
+
+      if config.default.as_deref() == Some(&name) {
+        config.default = None;
+        config.save(self.config_file())?;
+        removal.default_cleared = Some(name.clone());
+      }
+
+      let removed = aliases.remove_version(&name);
+      if !removed.is_empty() {
+        aliases.save(self.aliases_file())?;
+        removal.aliases_removed.extend(removed);
+      }
+      removal.removed.push(name);
+    }
+
+    if removal.was_active {
+      removal.fallback = self.fall_back_to_default(&config).await?;
+    }
+
+    Ok(removal)
+  }
+
+  /// Link the default version after the active one is gone, or remove the dangling link
+  async fn fall_back_to_default(&self, config: &Config) -> Maybe<Option<String>> {
+    let default = config.default.as_ref().map(|name| SourcePHP::new(self.archives().join(name)));
+
+    match default {
+      Some(default) if default.is_installed() => {
+        self.link(&default).await?;
+        Ok(Some(default.name().into_owned()))
+      }
+      _ => {
+        std::fs::remove_file(self.bin()).or_else(|_| std::fs::remove_dir_all(self.bin())).ok();
+        Ok(None)
+      }
+    }
+  }
+}
+
+#[test]
+fn test_resolve() {
+  let work_dir = std::env::temp_dir().join(format!("maphp-manager-{}", std::process::id()));
+  let manager = Manager::new(&work_dir, Options::default()).unwrap();
+  std::fs::create_dir_all(manager.archives().join("8.3.0")).unwrap();
+  let mut aliases = Aliases::default();
+  aliases.set("prod", "8.3.0");
+  aliases.save(manager.aliases_file()).unwrap();
+
+  assert_eq!(manager.resolve("prod").unwrap().name(), "8.3.0");
+  assert_eq!(manager.resolve("8.2.0").unwrap().src(), manager.archives().join("8.2.0"));
+  assert!(manager.list().unwrap().is_empty(), "partial sources aren't listed");
+
+  std::fs::remove_dir_all(work_dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_current_thread_runtime() {
+  let work_dir = std::env::temp_dir().join(format!("maphp-manager-rt-{}", std::process::id()));
+  let manager = Manager::new(&work_dir, Options::default()).unwrap();
+
+  let missing = work_dir.join("php-8.3.0-missing.tar.zst");
+  assert!(manager.install_package(&missing).await.is_err());
+
+  std::fs::create_dir_all(manager.archives().join("8.3.0")).unwrap();
+  let partial = manager.use_version("8.3.0").await.unwrap_err();
+  assert_eq!(partial.to_string(), "Version `8.3.0` is not installed");
+  assert!(!manager.bin().exists());
+
+  let removal = manager.remove(&[manager.resolve("8.3.0").unwrap()]).await.unwrap();
+  assert_eq!(removal.removed, ["8.3.0"]);
+
+  std::fs::remove_dir_all(work_dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_reject_paths_outside_archives() {
+  let work_dir = std::env::temp_dir().join(format!("maphp-manager-paths-{}", std::process::id()));
+  let manager = Manager::new(&work_dir, Options::default()).unwrap();
+  let outside = work_dir.join("victim");
+  std::fs::create_dir_all(&outside).unwrap();
+
+  for version in ["..", "../victim", "8.3.0/..", "/", outside.to_str().unwrap(), ""] {
+    assert!(manager.resolve(version).is_err(), "{version:?}");
+  }
+  let mut aliases = Aliases::default();
+  aliases.set("evil", "..");
+  aliases.save(manager.aliases_file()).unwrap();
+  assert!(manager.resolve("evil").is_err());
+
+  for src in [outside.clone(), manager.archives().join(".."), manager.archives()] {
+    assert!(manager.remove(&[SourcePHP::new(src)]).await.is_err());
+  }
+  assert!(outside.is_dir() && manager.archives().is_dir());
+
+  std::fs::remove_dir_all(work_dir).unwrap();
+}
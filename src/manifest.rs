@@ -0,0 +1,85 @@
+use crate::Maybe;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Options a version was compiled with, recorded so it can be rebuilt the same way
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct BuildOptions {
+  /// Arguments passed to `./configure`, without `--prefix` and `--enable-debug`
+  pub configure_args: Vec<String>,
+  /// Debug build, also selects `php.ini-development`
+  pub debug: bool,
+}
+
+/// `./configure` arguments of `maphp install` without any option
+pub const DEFAULT_CONFIGURE_ARGS: &[&str] = &[
+  "--enable-calendar",
+  "--enable-intl",
+  "--enable-mbstring",
+  "--enable-pcntl",
+  "--enable-bcmath",
+  "--with-curl",
+  "--with-openssl",
+  "--with-pear",
+  "--with-zip",
+  "--with-zlib",
+  "--with-password-argon2",
+];
+
+impl BuildOptions {
+  /// Whether the arguments are the ones of a plain `maphp install`
+  pub fn is_default(&self) -> bool {
+    self.configure_args == DEFAULT_CONFIGURE_ARGS
+  }
+
+  /// Recover the options of a build made before manifests existed from its `php-config`
+  pub fn from_php_config(php_config: &str) -> Option<Self> {
+    let line = php_config.lines().find(|line| line.starts_with("configure_options="))?;
+    let options = line.trim_start_matches("configure_options=").trim_matches('"');
+
+    let mut configure_args = vec![];
+    let mut debug = false;
+    for arg in options.split_whitespace().map(|arg| arg.trim_matches('\'')) {
+      match arg {
+        "--enable-debug" => debug = true,
+        arg if arg.starts_with("--prefix") || arg.is_empty() => {}
+        arg if arg.starts_with("--with-config-file-scan-dir") && arg.ends_with("/dist/lib/conf.d") => {}
+        arg => configure_args.push(arg.to_owned()),
+      }
+    }
+
+    Some(Self { configure_args, debug })
+  }
+}
+
+/// `build.json` stored in the source directory of each installed version
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct BuildManifest {
+  pub version: String,
+  pub options: BuildOptions,
+  /// Unix seconds of the build
+  pub built_at: u64,
+}
+
+impl BuildManifest {
+  pub fn path(src: impl AsRef<Path>) -> PathBuf {
+    src.as_ref().join("build.json")
+  }
+
+  pub fn load(src: impl AsRef<Path>) -> Option<Self> {
+    let reader = std::fs::File::open(Self::path(src)).ok()?;
+    serde_json::from_reader(reader).ok()
+  }
+
+  pub fn save(&self, src: impl AsRef<Path>) -> Maybe<()> {
+    crate::disk::atomic_write(Self::path(src), serde_json::to_vec_pretty(self)?)
+  }
+}
+
+#[test]
+fn test_from_php_config() {
+  let php_config = r#"configure_options=" '--prefix=/home/u/.maphp/archives/8.3.13/dist' '--enable-intl' '--with-pgsql=/opt/pg' '--enable-debug'""#;
+  let options = BuildOptions::from_php_config(php_config).unwrap();
+  assert!(options.debug);
+  assert_eq!(options.configure_args, ["--enable-intl", "--with-pgsql=/opt/pg"]);
+}
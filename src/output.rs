@@ -0,0 +1,161 @@
+use crate::Maybe;
+use crate::progress::ProgressMode;
+use crate::source::VersionInfo;
+use clap::{Args, ValueEnum};
+use colored::Colorize;
+use indicatif::HumanBytes;
+use maphp::ProgressEvent;
+use serde::Serialize;
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Format {
+  /// Human readable output
+  #[default]
+  Table,
+  /// One value per line, suitable for scripts
+  Plain,
+  /// JSON document
+  Json,
+}
+
+#[derive(Args, Clone, Debug)]
+pub struct FormatArgs {
+  /// Output format
+  #[arg(long, value_enum, default_value_t = Format::Table)]
+  format: Format,
+
+  /// Shorthand for `--format json`
+  #[arg(long, default_value_t = false, conflicts_with = "format")]
+  json: bool,
+}
+
+impl FormatArgs {
+  pub fn format(&self) -> Format {
+    if self.json { Format::Json } else { self.format }
+  }
+}
+
+/// Print `value` as pretty JSON on stdout
+pub fn print_json(value: &impl Serialize) -> Maybe<()> {
+  println!("{}", serde_json::to_string_pretty(value)?);
+  Ok(())
+}
+
+/// Print rows aligned on columns, `headers` is printed first
+pub fn print_table(headers: &[&str], rows: &[Vec<String>]) {
+  let mut widths = headers.iter().map(|h| h.chars().count()).collect::<Vec<_>>();
+  for row in rows {
+    for (width, cell) in widths.iter_mut().zip(row) {
+      *width = (*width).max(cell.chars().count());
+    }
+  }
+
+  let line = |cells: &mut dyn Iterator<Item = &str>| {
+    let line = cells.zip(&widths).map(|(cell, width)| format!("{cell:<width$}")).collect::<Vec<_>>();
+    println!("{}", line.join("  ").trim_end());
+  };
+
+  line(&mut headers.iter().copied());
+  for row in rows {
+    line(&mut row.iter().map(String::as_str));
+  }
+}
+
+/// Print installed or available versions in the requested format
+pub fn print_versions(versions: &[VersionInfo], format: Format) -> Maybe<()> {
+  match format {
+    Format::Json => print_json(&versions)?,
+    Format::Plain => versions.iter().for_each(|v| println!("{}", v.version)),
+    Format::Table => {
+      let rows = versions
+        .iter()
+        .map(|v| {
+          vec![
+            v.version.clone(),
+            v.stability.as_str().to_owned(),
+            v.variant.clone().unwrap_or_default(),
+            v.support.map(|s| s.as_str().to_owned()).unwrap_or_default(),
+            if v.active { "*".to_owned() } else { String::new() },
+            if v.default { "*".to_owned() } else { String::new() },
+            v.size.map(|size| HumanBytes(size).to_string()).unwrap_or_default(),
+            v.installed_at.clone().unwrap_or_default(),
+            v.path.as_ref().map(|p| p.display().to_string()).unwrap_or_default(),
+          ]
+        })
+        .collect::<Vec<_>>();
+      print_table(
+        &[
+          "VERSION",
+          "STABILITY",
+          "VARIANT",
+          "SUPPORT",
+          "ACTIVE",
+          "DEFAULT",
+          "SIZE",
+          "INSTALLED",
+          "PATH",
+        ],
+        &rows,
+      );
+    }
+  }
+
+  Ok(())
+}
+
+/// Print a single version in the requested format, plain output is `key=value` lines
+pub fn print_version(version: &VersionInfo, format: Format) -> Maybe<()> {
+  if format == Format::Json {
+    return print_json(version);
+  }
+
+  let size = match format {
+    Format::Plain => version.size.map(|size| size.to_string()),
+    _ => version.size.map(|size| HumanBytes(size).to_string()),
+  };
+  let fields = [
+    ("version", version.version.clone()),
+    ("stability", version.stability.as_str().to_owned()),
+    ("variant", version.variant.clone().unwrap_or_default()),
+    ("support", version.support.map(|s| s.as_str().to_owned()).unwrap_or_default()),
+    ("released_at", version.released_at.clone().unwrap_or_default()),
+    ("active", version.active.to_string()),
+    ("default", version.default.to_string()),
+    ("broken", version.broken.to_string()),
+    ("path", version.path.as_ref().map(|p| p.display().to_string()).unwrap_or_default()),
+    ("installed_at", version.installed_at.clone().unwrap_or_default()),
+    ("size", size.unwrap_or_default()),
+  ];
+
+  for (key, value) in fields {
+    match format {
+      Format::Plain => println!("{key}={value}"),
+      _ => println!("{key:<13}{value}"),
+    }
+  }
+
+  Ok(())
+}
+
+/// Print a warning that doesn't stop the command on stderr, so stdout stays parseable
+pub fn print_warning(message: impl std::fmt::Display) {
+  eprintln!("{}", format!("⚠️ {message}").yellow());
+}
+
+/// Report a step of the manager: a JSON line on stderr in `json` mode,
+/// warnings and verifications on stdout in the other modes but `silent`
+pub fn print_event(mode: ProgressMode, event: &ProgressEvent) {
+  match (mode, event) {
+    (ProgressMode::Json, event) => {
+      if let Ok(serde_json::Value::Object(mut line)) = serde_json::to_value(event) {
+        let time = crate::date::rfc3339(crate::date::unix_now());
+        line.insert("time".to_owned(), time.into());
+        eprintln!("{}", serde_json::Value::Object(line));
+      }
+    }
+    (ProgressMode::Silent, _) => {}
+    (_, ProgressEvent::Warning { message, .. }) => print_warning(message),
+    (_, ProgressEvent::Verified { version }) => println!("✅ Verified PHP {version}"),
+    _ => {}
+  }
+}
@@ -0,0 +1,309 @@
+use crate::Maybe;
+use crate::manifest::BuildManifest;
+use crate::source::SourcePHP;
+use crate::stats::Version;
+use anyhow::{Context, bail, ensure};
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// `package.json`, always the first entry of a package
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PackageInfo {
+  pub version: String,
+  /// `dist` directory the build was packed from, rewritten when installed elsewhere
+  pub prefix: PathBuf,
+  /// `<arch>-<os>` the binaries were built for
+  pub target: String,
+  #[serde(default)]
+  pub libc: String,
+  /// Unix seconds of packing
+  pub packed_at: u64,
+}
+
+/// Platform packages are built for and can be installed on
+pub fn target() -> String {
+  format!("{}-{}", std::env::consts::ARCH, std::env::consts::OS)
+}
+
+/// C library of this machine, empty where it isn't a choice.
+///
+/// Detected once at runtime from the musl loader in `/lib`, `MAPHP_LIBC` overrides it.
+pub fn libc() -> &'static str {
+  static LIBC: OnceLock<String> = OnceLock::new();
+  LIBC.get_or_init(|| match std::env::var("MAPHP_LIBC") {
+    Ok(libc) if !libc.is_empty() => libc,
+    _ if !cfg!(target_os = "linux") => String::new(),
+    _ => {
+      let musl = std::fs::read_dir("/lib")
+        .map(|entries| entries.flatten().any(|entry| entry.file_name().to_string_lossy().starts_with("ld-musl-")))
+        .unwrap_or(false);
+      if musl { "musl" } else { "gnu" }.to_owned()
+    }
+  })
+}
+
+/// Write `dist` and the build manifest of `source` into `output`.
+///
+/// `.tar.gz` and `.tgz` outputs are gzip compressed, anything else zstd.
+pub fn pack(source: &SourcePHP, output: &Path) -> Maybe<()> {
+  ensure!(source.is_installed(), "Version `{}` is not installed", source.name());
+
+  let info = PackageInfo {
+    version: source.name().into_owned(),
+    prefix: source.dist(),
+    target: target(),
+    libc: libc().to_owned(),
+    packed_at: crate::date::unix_now(),
+  };
+  let manifest = match BuildManifest::load(source.src()) {
+    Some(manifest) => manifest,
+    None => BuildManifest {
+      version: info.version.clone(),
+      options: source.build_options().unwrap_or_default(),
+      built_at: 0,
+    },
+  };
+
+  let file = std::fs::File::create(output).with_context(|| format!("Couldn't create {}", output.display()))?;
+  let name = output.file_name().unwrap_or_default().to_string_lossy();
+  if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+    let encoder = write_tar(
+      flate2::write::GzEncoder::new(file, flate2::Compression::default()),
+      source,
+      &info,
+      &manifest,
+    )?;
+    encoder.finish()?.sync_all()?;
+  } else {
+    let encoder = write_tar(zstd::Encoder::new(file, zstd::DEFAULT_COMPRESSION_LEVEL)?, source, &info, &manifest)?;
+    encoder.finish()?.sync_all()?;
+  }
+
+  Ok(())
+}
+
+fn write_tar<W: Write>(writer: W, source: &SourcePHP, info: &PackageInfo, manifest: &BuildManifest) -> Maybe<W> {
+  let mut builder = tar::Builder::new(writer);
+  builder.follow_symlinks(false);
+
+  for (name, content) in [
+    ("package.json", serde_json::to_vec_pretty(info)?),
+    ("build.json", serde_json::to_vec_pretty(manifest)?),
+  ] {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(content.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(info.packed_at);
+    builder.append_data(&mut header, name, content.as_slice())?;
+  }
+  builder.append_dir_all("dist", source.dist())?;
+
+  Ok(builder.into_inner()?)
+}
+
+/// Install the package at `path` into the `archives` directory and relocate it there.
+///
+/// The package must contain `expected`, or the version in its file name (`php-<version>-…`) when there is one.
+/// An existing version is only replaced when `force` is set.
+pub fn unpack(path: &Path, archives: &Path, expected: Option<&str>, force: bool) -> Maybe<SourcePHP> {
+  let file = std::fs::File::open(path).with_context(|| format!("Couldn't open {}", path.display()))?;
+  let named = path.file_name().and_then(|name| version_in_name(&name.to_string_lossy()));
+  unpack_from(file, &path.display().to_string(), archives, expected.or(named.as_deref()), force)
+}
+
+/// Version of a package named `php-<version>-<target>…`
+fn version_in_name(name: &str) -> Option<String> {
+  let version = name.strip_prefix("php-")?.split('-').next()?;
+  let version = version.strip_suffix(".tar.zst").or(version.strip_suffix(".tar.gz")).unwrap_or(version);
+  version.parse::<Version>().ok().map(|_| version.to_owned())
+}
+
+/// Directory of the package `version` in `archives`, which must be a plain PHP version
+fn version_dir(archives: &Path, version: &str) -> Maybe<PathBuf> {
+  match crate::disk::child(archives, version) {
+    Some(dir) if version.parse::<Version>().is_ok() => Ok(dir),
+    _ => bail!("Invalid package version `{version}`"),
+  }
+}
+
+/// Same as [`unpack`] with the package read from `reader`, `name` is used in errors
+pub fn unpack_from(reader: impl Read, name: &str, archives: &Path, expected: Option<&str>, force: bool) -> Maybe<SourcePHP> {
+  let mut reader = BufReader::new(reader);
+  let reader: Box<dyn Read> = match reader.fill_buf()? {
+    [0x1f, 0x8b, ..] => Box::new(flate2::read::GzDecoder::new(reader)),
+    [0x28, 0xb5, 0x2f, 0xfd, ..] => Box::new(zstd::Decoder::with_buffer(reader)?),
+    _ => bail!("{name} is neither a gzip nor a zstd package"),
+  };
+
+  let mut archive = tar::Archive::new(reader);
+  archive.set_preserve_permissions(true);
+  archive.set_preserve_mtime(true);
+  let mut entries = archive.entries()?;
+
+  let info: PackageInfo = match entries.next() {
+    Some(entry) if entry.as_ref().is_ok_and(|e| e.path().is_ok_and(|p| p == Path::new("package.json"))) => serde_json::from_reader(entry?)?,
+    _ => bail!("{name} is not a maphp package, package.json is missing"),
+  };
+  ensure!(
+    info.target == target() && (info.libc.is_empty() || info.libc == libc()),
+    "Package {} was built for {} {}, this machine is {} {}",
+    info.version,
+    info.target,
+    info.libc,
+    target(),
+    libc()
+  );
+
+  let dir = version_dir(archives, &info.version).with_context(|| format!("{name} is not a valid maphp package"))?;
+  if let Some(expected) = expected {
+    ensure!(info.version == expected, "{name} contains PHP {}, expected {expected}", info.version);
+  }
+
+  let source = SourcePHP::new(dir);
+  if source.src().exists() {
+    ensure!(force, "Version `{}` already exists, pass --force to replace it", info.version);
+    std::fs::remove_dir_all(source.src())?;
+  }
+  let _lock = source.lock()?;
+
+  let result = entries
+    .map(|entry| Ok(entry?.unpack_in(source.src())?))
+    .collect::<Maybe<Vec<_>>>()
+    .and_then(|_| relocate(&source.dist(), &info.prefix, &source.dist()))
+    .and_then(|_| configure_relocated_ini(&source));
+
+  if let Err(err) = result {
+    _ = std::fs::remove_dir_all(source.src());
+    return Err(err.context(format!("Couldn't install package {name}")));
+  }
+
+  Ok(source)
+}
+
+/// Replace the `from` prefix with `to` in the text files of `dist` that hardcode it:
+/// scripts in `bin` such as `php-config` and `phpize`, `etc` (incl. `pear.conf`), `lib/php/build` and ini files.
+///
+/// Returns the number of rewritten files.
+pub fn relocate(dist: &Path, from: &Path, to: &Path) -> Maybe<usize> {
+  if from == to {
+    return Ok(0);
+  }
+
+  let (from, to) = (from.to_string_lossy(), to.to_string_lossy());
+  let mut candidates = vec![];
+  crate::disk::walk(dist, &mut |path, _| {
+    let relative = path.strip_prefix(dist).unwrap_or(path);
+    let is_config = path.extension().is_some_and(|ext| ext == "ini" || ext == "conf");
+    if is_config || ["bin", "etc", "lib/php/build"].iter().any(|dir| relative.starts_with(dir)) {
+      candidates.push(path.to_path_buf());
+    }
+  });
+
+  let mut rewritten = 0;
+  for path in candidates {
+    let content = std::fs::read(&path)?;
+    // Binaries embed the prefix too but their strings can't change length
+    if content.contains(&0) {
+      continue;
+    }
+    let Ok(content) = String::from_utf8(content) else { continue };
+    if !content.contains(&*from) {
+      continue;
+    }
+
+    let content = match path.file_name().is_some_and(|name| name == "pear.conf") {
+      true => rewrite_serialized(&content, &from, &to),
+      false => content.replace(&*from, &to),
+    };
+    std::fs::write(&path, content).with_context(|| format!("Couldn't rewrite {}", path.display()))?;
+    rewritten += 1;
+  }
+
+  Ok(rewritten)
+}
+
+/// Replace `from` with `to` in the strings of a PHP `serialize()` payload, fixing their `s:<len>:` prefix
+fn rewrite_serialized(content: &str, from: &str, to: &str) -> String {
+  let mut output = String::with_capacity(content.len());
+  let mut rest = content;
+
+  while let Some(at) = rest.find("s:") {
+    output.push_str(&rest[..at]);
+    rest = &rest[at + 2..];
+
+    let digits = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+    let value = rest[..digits]
+      .parse::<usize>()
+      .ok()
+      .filter(|_| rest[digits..].starts_with(":\""))
+      .and_then(|len| rest.get(digits + 2..digits + 2 + len));
+
+    match value {
+      Some(value) => {
+        let replaced = value.replace(from, to);
+        output.push_str(&format!("s:{}:\"{replaced}", replaced.len()));
+        rest = &rest[digits + 2 + value.len()..];
+      }
+      None => output.push_str("s:"),
+    }
+  }
+
+  output.push_str(rest);
+  output
+}
+
+/// Marker of the settings added to `php.ini`, everything after it is replaced on each relocation
+const RELOCATED_MARKER: &str = "; Added by maphp, the paths compiled into php point to where the build was made";
+
+/// Point `extension_dir` and `include_path` of `php.ini` at the new prefix and make it found.
+///
+/// The binary still looks for `php.ini` in the original prefix, the CLI also looks next to itself
+/// so `bin/php.ini` links to `lib/php.ini`.
+pub fn configure_relocated_ini(source: &SourcePHP) -> Maybe<()> {
+  let dist = source.dist();
+  let ini = dist.join("lib/php.ini");
+  let content = std::fs::read_to_string(&ini).unwrap_or_default();
+  let content = content.split(RELOCATED_MARKER).next().unwrap_or_default().trim_end();
+
+  let mut settings = format!("{content}\n\n{RELOCATED_MARKER}\n");
+  if let Some(dir) = source.extension_dir() {
+    settings.push_str(&format!("extension_dir = \"{}\"\n", dir.display()));
+  }
+  settings.push_str(&format!("include_path = \".:{}\"\n", dist.join("lib/php").display()));
+  std::fs::write(&ini, settings.trim_start())?;
+
+  #[cfg(unix)]
+  if std::fs::symlink_metadata(dist.join("bin/php.ini")).is_err() {
+    std::os::unix::fs::symlink("../lib/php.ini", dist.join("bin/php.ini"))?;
+  }
+
+  Ok(())
+}
+
+#[test]
+fn test_rewrite_serialized() {
+  let conf = r#"#PEAR_Config 0.9
+a:2:{s:7:"php_dir";s:24:"/old/prefix/dist/lib/php";s:7:"bin_dir";s:20:"/old/prefix/dist/bin";}"#;
+  let rewritten = rewrite_serialized(conf, "/old/prefix", "/new");
+
+  assert_eq!(
+    rewritten,
+    r#"#PEAR_Config 0.9
+a:2:{s:7:"php_dir";s:17:"/new/dist/lib/php";s:7:"bin_dir";s:13:"/new/dist/bin";}"#
+  );
+}
+
+#[test]
+fn test_version_dir() {
+  let archives = Path::new("/maphp/archives");
+  assert_eq!(version_dir(archives, "8.3.13").unwrap(), archives.join("8.3.13"));
+  for version in ["../..", "/etc", "8.3.13/../../x", "..", "", "latest"] {
+    assert!(version_dir(archives, version).is_err(), "{version}");
+  }
+
+  assert_eq!(version_in_name("php-8.3.13-x86_64-linux.tar.zst").as_deref(), Some("8.3.13"));
+  assert_eq!(version_in_name("php-8.4.0RC1.tar.gz").as_deref(), Some("8.4.0RC1"));
+  assert_eq!(version_in_name("build.tar.zst"), None);
+}
@@ -0,0 +1,245 @@
+use crate::static_const::{FPS, PROGRESS};
+use clap::ValueEnum;
+use indicatif::{HumanBytes, ProgressBar, ProgressFinish, ProgressState, ProgressStyle};
+use serde::Serialize;
+use std::io::IsTerminal;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// How long running steps (downloads, builds, packing) are reported, on stderr
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ProgressMode {
+  /// `tty` on an interactive terminal outside CI, `plain` otherwise
+  #[default]
+  Auto,
+  /// Spinners and bars redrawn in place
+  Tty,
+  /// One timestamped line per step, for CI logs
+  Plain,
+  /// One JSON object per line, for other tools
+  Json,
+  /// Only errors
+  Silent,
+}
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+impl ProgressMode {
+  /// `Auto` resolved against stderr and the `CI` env
+  pub fn resolve(self) -> Self {
+    match self {
+      Self::Auto if std::io::stderr().is_terminal() && std::env::var_os("CI").is_none() => Self::Tty,
+      Self::Auto => Self::Plain,
+      mode => mode,
+    }
+  }
+}
+
+/// Change of a reporter, `json` lines carry its name as `event`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Change {
+  Step,
+  Log,
+  Finish,
+  Fail,
+}
+
+#[derive(Serialize)]
+struct Line<'a> {
+  time: String,
+  task: u64,
+  event: &'static str,
+  text: &'a str,
+}
+
+/// Line written for `change` in `plain` and `json` modes, `None` in the others
+fn render(mode: ProgressMode, task: u64, change: Change, text: &str) -> Option<String> {
+  let time = crate::date::rfc3339(crate::date::unix_now());
+  match mode {
+    ProgressMode::Plain => match change {
+      Change::Fail => Some(format!("[{time}] failed: {text}")),
+      _ => Some(format!("[{time}] {text}")),
+    },
+    ProgressMode::Json => {
+      let event = match change {
+        Change::Step => "step",
+        Change::Log => "log",
+        Change::Finish => "finish",
+        Change::Fail => "fail",
+      };
+      serde_json::to_string(&Line { time, task, event, text }).ok()
+    }
+    _ => None,
+  }
+}
+
+enum Output {
+  Tty(ProgressBar),
+  Lines(ProgressMode),
+  Silent,
+}
+
+struct State {
+  id: u64,
+  output: Output,
+  prefix: Mutex<String>,
+  position: AtomicU64,
+}
+
+/// Handle reporting one long running task in the selected [`ProgressMode`], clones report on the same task
+#[derive(Clone)]
+pub struct Reporter(Arc<State>);
+
+impl std::fmt::Debug for Reporter {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("Reporter")
+      .field("id", &self.0.id)
+      .field("prefix", &*self.0.prefix.lock().unwrap())
+      .finish_non_exhaustive()
+  }
+}
+
+impl Reporter {
+  const SPINNER: &'static str = "{spinner:.green} {prefix}\n\
+  {wide_msg}";
+
+  fn with_bar(mode: ProgressMode, bar: impl FnOnce() -> ProgressBar) -> Self {
+    let output = match mode.resolve() {
+      ProgressMode::Tty => {
+        let bar = PROGRESS.add(bar());
+        bar.enable_steady_tick(Duration::from_secs_f32(FPS));
+        Output::Tty(bar)
+      }
+      ProgressMode::Silent => Output::Silent,
+      mode => Output::Lines(mode),
+    };
+
+    Self(Arc::new(State {
+      id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+      output,
+      prefix: Mutex::default(),
+      position: AtomicU64::new(0),
+    }))
+  }
+
+  /// Spinner showing the current step and the last output line under it
+  pub fn spinner(mode: ProgressMode) -> Self {
+    Self::with_bar(mode, || {
+      ProgressBar::new_spinner()
+        .with_style(ProgressStyle::default_spinner().template(Self::SPINNER).unwrap())
+        .with_finish(ProgressFinish::AndLeave)
+    })
+  }
+
+  /// Downloaded bytes and speed
+  pub fn download(mode: ProgressMode) -> Self {
+    Self::with_bar(mode, || {
+      let style = ProgressStyle::with_template("{spinner:.green} [{bar:30.cyan/blue}] {bytes} | {speed}")
+        .unwrap()
+        .with_key("speed", |s: &ProgressState, w: &mut dyn std::fmt::Write| {
+          let persec = s.per_sec().abs() as u64;
+          write!(w, "{}/s", HumanBytes(persec)).unwrap()
+        })
+        .progress_chars("#>-");
+      ProgressBar::new_spinner().with_style(style)
+    })
+  }
+
+  fn report(&self, change: Change, text: &str) {
+    if let Output::Lines(mode) = self.0.output
+      && let Some(line) = render(mode, self.0.id, change, text)
+    {
+      eprintln!("{line}");
+    }
+  }
+
+  /// Current step, a new line in `plain` and `json` modes
+  pub fn set_prefix(&self, prefix: impl Into<String>) {
+    let prefix = prefix.into();
+    match self.0.output {
+      Output::Tty(ref bar) => bar.set_prefix(prefix.clone()),
+      _ => self.report(Change::Step, &prefix),
+    }
+    *self.0.prefix.lock().unwrap() = prefix;
+  }
+
+  /// Transient detail of the step, e.g. the last build output line. Only shown on a terminal
+  pub fn set_message(&self, message: impl Into<String>) {
+    if let Output::Tty(ref bar) = self.0.output {
+      bar.set_message(message.into());
+    }
+  }
+
+  /// Output worth keeping, e.g. warnings
+  pub fn println(&self, line: impl AsRef<str>) {
+    match self.0.output {
+      Output::Tty(ref bar) => bar.println(line),
+      _ => self.report(Change::Log, line.as_ref()),
+    }
+  }
+
+  pub fn inc(&self, delta: u64) {
+    self.0.position.fetch_add(delta, Ordering::Relaxed);
+    if let Output::Tty(ref bar) = self.0.output {
+      bar.inc(delta);
+    }
+  }
+
+  pub fn position(&self) -> u64 {
+    self.0.position.load(Ordering::Relaxed)
+  }
+
+  fn done(&self, change: Change, message: String) {
+    match self.0.output {
+      Output::Tty(ref bar) if change == Change::Fail => bar.abandon_with_message(message),
+      Output::Tty(ref bar) if message.is_empty() => bar.finish(),
+      Output::Tty(ref bar) => bar.finish_with_message(message),
+      _ => {
+        // The step was already reported, a failure needs it again for context
+        let prefix = self.0.prefix.lock().unwrap().clone();
+        let text = match (change, message.is_empty()) {
+          (Change::Fail, _) if !prefix.is_empty() => format!("{prefix}: {message}"),
+          (_, true) => format!("{prefix}: done"),
+          _ => message,
+        };
+        self.report(change, &text);
+      }
+    }
+  }
+
+  pub fn finish(&self) {
+    self.done(Change::Finish, String::new());
+  }
+
+  pub fn finish_with_message(&self, message: impl Into<String>) {
+    self.done(Change::Finish, message.into());
+  }
+
+  pub fn abandon_with_message(&self, message: impl Into<String>) {
+    self.done(Change::Fail, message.into());
+  }
+
+  /// Remove the spinner, nothing is reported in the other modes
+  pub fn finish_and_clear(&self) {
+    if let Output::Tty(ref bar) = self.0.output {
+      bar.finish_and_clear();
+    }
+  }
+}
+
+#[test]
+fn test_render() {
+  let plain = render(ProgressMode::Plain, 1, Change::Step, "8.3.0: make install").unwrap();
+  assert!(plain.starts_with('[') && plain.ends_with("Z] 8.3.0: make install"));
+  assert!(render(ProgressMode::Plain, 1, Change::Fail, "boom").unwrap().ends_with("] failed: boom"));
+
+  let json = render(ProgressMode::Json, 7, Change::Log, "warning").unwrap();
+  let json: serde_json::Value = serde_json::from_str(&json).unwrap();
+  assert_eq!(
+    (json["task"].as_u64(), json["event"].as_str(), json["text"].as_str()),
+    (Some(7), Some("log"), Some("warning"))
+  );
+
+  assert_eq!(render(ProgressMode::Silent, 1, Change::Log, "hidden"), None);
+}
@@ -0,0 +1,225 @@
+use crate::Maybe;
+use crate::manifest::BuildOptions;
+use crate::package::{libc, target, unpack_from};
+use crate::progress::Reporter;
+use crate::source::SourcePHP;
+use anyhow::{Context, bail, ensure};
+use indicatif::HumanBytes;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// Where prebuilt packages are looked up, both hold an `index.json` next to the packages
+#[derive(Debug, Clone)]
+pub enum Repository {
+  Http(Url),
+  Local(PathBuf),
+}
+
+/// `index.json` of a repository
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct Index {
+  #[serde(default)]
+  pub packages: Vec<Entry>,
+}
+
+/// A package made by `maphp pack`
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Entry {
+  pub version: String,
+  /// `<arch>-<os>`, see [`crate::package::target`]
+  pub target: String,
+  /// `gnu` or `musl` on Linux, empty elsewhere
+  #[serde(default)]
+  pub libc: String,
+  /// `release` or `debug`
+  pub profile: String,
+  /// `./configure` arguments of the build, matches any options when missing
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub configure_args: Option<Vec<String>>,
+  /// Package path relative to `index.json`
+  pub file: String,
+  /// Hex encoded SHA-256 of the package
+  pub sha256: String,
+}
+
+/// Profile of a build, `debug` builds never match `release` packages
+pub fn profile(options: &BuildOptions) -> &'static str {
+  if options.debug { "debug" } else { "release" }
+}
+
+impl Index {
+  pub fn load(path: impl AsRef<Path>) -> Maybe<Self> {
+    match std::fs::read(path.as_ref()) {
+      Ok(content) => serde_json::from_slice(&content).with_context(|| format!("Invalid index {}", path.as_ref().display())),
+      Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+      Err(err) => Err(err.into()),
+    }
+  }
+
+  pub fn save(&self, path: impl AsRef<Path>) -> Maybe<()> {
+    crate::disk::atomic_write(path, serde_json::to_vec_pretty(self)?)
+  }
+
+  /// Package of `version` built with `options` for this machine, entries without `configure_args` are default builds
+  pub fn find(&self, version: &str, options: &BuildOptions) -> Option<&Entry> {
+    self.packages.iter().find(|entry| {
+      entry.version == version
+        && entry.target == target()
+        && entry.libc == libc()
+        && entry.profile == profile(options)
+        && entry
+          .configure_args
+          .as_ref()
+          .map_or(options.is_default(), |args| *args == options.configure_args)
+    })
+  }
+
+  /// Add `entry`, replacing a package of the same version, platform and options
+  pub fn upsert(&mut self, entry: Entry) {
+    self.packages.retain(|known| {
+      (&known.version, &known.target, &known.libc, &known.profile, &known.configure_args)
+        != (&entry.version, &entry.target, &entry.libc, &entry.profile, &entry.configure_args)
+    });
+    self.packages.push(entry);
+  }
+}
+
+impl Repository {
+  /// `http://` and `https://` values are URLs, anything else a directory
+  pub fn parse(value: &str) -> Maybe<Self> {
+    if value.starts_with("http://") || value.starts_with("https://") {
+      let value = if value.ends_with('/') { value.to_owned() } else { format!("{value}/") };
+      Ok(Self::Http(Url::parse(&value)?))
+    } else {
+      Ok(Self::Local(PathBuf::from(value)))
+    }
+  }
+
+  pub async fn index(&self) -> Maybe<Index> {
+    match self {
+      Self::Http(base) => {
+        let url = base.join("index.json")?;
+        let response = client()?.get(url.clone()).send().await?.error_for_status()?;
+        response.json().await.with_context(|| format!("Invalid index {url}"))
+      }
+      Self::Local(dir) => Index::load(dir.join("index.json")),
+    }
+  }
+
+  /// Install the package matching `version` and `options` into `archives`, `None` when there is none.
+  ///
+  /// An unreachable repository is reported and treated as empty, a checksum mismatch is an error.
+  pub async fn install(&self, archives: &Path, version: &str, options: &BuildOptions, progress: &Reporter) -> Maybe<Option<SourcePHP>> {
+    let index = match self.index().await {
+      Ok(index) => index,
+      Err(err) => {
+        progress.println(format!("⚠️ Binary repository unavailable, building from source: {err:#}"));
+        return Ok(None);
+      }
+    };
+    let Some(entry) = index.find(version, options) else {
+      return Ok(None);
+    };
+
+    ensure!(
+      entry.version == version,
+      "Index entry {} is for version {}, expected {version}",
+      entry.file,
+      entry.version
+    );
+
+    // Hashed and unpacked from the same bytes so the package can't change in between
+    let (package, name) = match self {
+      Self::Http(base) => {
+        let url = base.join(&entry.file)?;
+        progress.set_prefix(format!("{version}: downloading prebuilt"));
+        match download(&url).await {
+          Ok(package) => (package, url.to_string()),
+          Err(err) => {
+            progress.println(format!("⚠️ Couldn't download {url}, building from source: {err:#}"));
+            return Ok(None);
+          }
+        }
+      }
+      Self::Local(dir) => {
+        let path = dir.join(&entry.file);
+        let package = std::fs::read(&path).with_context(|| format!("Couldn't open {}", path.display()))?;
+        (package, path.display().to_string())
+      }
+    };
+
+    verify(&sha256(package.as_slice())?, entry)?;
+    progress.set_prefix(format!("{version}: unpacking {}", HumanBytes(package.len() as u64)));
+    let (archives, version) = (archives.to_path_buf(), version.to_owned());
+    tokio::task::spawn_blocking(move || unpack_from(package.as_slice(), &name, &archives, Some(&version), true))
+      .await?
+      .map(Some)
+  }
+}
+
+fn client() -> Maybe<reqwest::Client> {
+  Ok(reqwest::Client::builder().user_agent(crate::static_const::USER_AGENT).build()?)
+}
+
+async fn download(url: &Url) -> Maybe<Vec<u8>> {
+  let response = client()?.get(url.clone()).send().await?.error_for_status()?;
+  Ok(response.bytes().await?.to_vec())
+}
+
+fn verify(actual: &str, entry: &Entry) -> Maybe<()> {
+  if !actual.eq_ignore_ascii_case(&entry.sha256) {
+    bail!("Checksum mismatch for {}: expected {}, got {actual}", entry.file, entry.sha256);
+  }
+  Ok(())
+}
+
+/// Hex encoded SHA-256 of everything read from `reader`
+pub fn sha256(mut reader: impl Read) -> std::io::Result<String> {
+  let mut context = ring::digest::Context::new(&ring::digest::SHA256);
+  let mut buffer = [0; 64 * 1024];
+  loop {
+    match reader.read(&mut buffer)? {
+      0 => break,
+      read => context.update(&buffer[..read]),
+    }
+  }
+
+  Ok(context.finish().as_ref().iter().map(|byte| format!("{byte:02x}")).collect())
+}
+
+#[test]
+fn test_find() {
+  let mut index = Index::default();
+  let entry = Entry {
+    version: "8.3.13".to_owned(),
+    target: target(),
+    libc: libc().to_owned(),
+    profile: "release".to_owned(),
+    configure_args: None,
+    file: "php-8.3.13.tar.zst".to_owned(),
+    sha256: sha256(b"package".as_slice()).unwrap(),
+  };
+  index.upsert(entry.clone());
+  index.upsert(entry.clone());
+  assert_eq!(index.packages.len(), 1);
+
+  let release = BuildOptions {
+    configure_args: crate::manifest::DEFAULT_CONFIGURE_ARGS.iter().map(|arg| arg.to_string()).collect(),
+    debug: false,
+  };
+  let debug = BuildOptions {
+    debug: true,
+    ..release.clone()
+  };
+  let custom = BuildOptions {
+    configure_args: vec!["--with-pgsql".to_owned()],
+    debug: false,
+  };
+  assert_eq!(index.find("8.3.13", &release), Some(&entry));
+  assert_eq!(index.find("8.3.13", &debug), None);
+  assert_eq!(index.find("8.3.13", &custom), None);
+  assert_eq!(index.find("8.3.12", &release), None);
+  assert_eq!(entry.sha256, "bc4a71180870f7945155fbb02f4b0a2e3faa2a62d6d31b7039013055ed19869a");
+}
@@ -1,140 +1,155 @@
-use crate::static_const::CLI;
-use crate::{Commands, Maybe};
+use crate::Maybe;
+use crate::config::Config;
+use crate::manifest::{BuildManifest, BuildOptions};
+use crate::progress::{ProgressMode, Reporter};
+use crate::stats::releases::{ReleaseIndex, Support};
+use crate::stats::{Stability, Version};
 use anyhow::ensure;
-use indicatif::{ProgressBar, ProgressFinish, ProgressStyle};
+use serde::Serialize;
 use std::borrow::Cow;
 use std::fmt::{Display, Formatter};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Stdio;
-use std::time::Duration;
 use tokio::io::{AsyncBufReadExt, BufReader};
 use tokio::process::Command;
 
 #[derive(Debug)]
-pub struct SourcePHP(
+pub struct SourcePHP {
   /// The path of PHP source code not the compiled
-  PathBuf,
-);
+  src: PathBuf,
+  /// Shared reporter, each build step is reported on it instead of a fresh spinner
+  progress: Option<Reporter>,
+  /// Number of `make` jobs
+  jobs: usize,
+  /// Print the whole build output instead of its last line
+  verbose: bool,
+  /// How the spinners of steps without a shared reporter report
+  mode: ProgressMode,
+}
 
 impl SourcePHP {
-  const TEMPLATE_STR: &'static str = "{spinner:.green} {prefix}\n\
-  {wide_msg}";
+  pub fn new(src: impl Into<PathBuf>) -> Self {
+    Self {
+      src: src.into(),
+      progress: None,
+      jobs: num_cpus::get(),
+      verbose: false,
+      mode: ProgressMode::Auto,
+    }
+  }
 
-  pub(crate) fn new_spinner() -> ProgressBar {
-    let spinner = ProgressBar::new_spinner()
-      .with_style(ProgressStyle::default_spinner().template(Self::TEMPLATE_STR).unwrap())
-      .with_finish(ProgressFinish::AndLeave);
-    spinner.enable_steady_tick(Duration::from_secs_f32(crate::static_const::FPS));
-    spinner
+  /// Report every build step on `progress` instead of creating a spinner per step
+  pub fn with_progress(mut self, progress: Reporter) -> Self {
+    self.progress = Some(progress);
+    self
   }
 
-  pub fn new(src: impl Into<PathBuf>) -> Self {
-    let spinner = ProgressBar::new_spinner().with_style(ProgressStyle::default_spinner().template(Self::TEMPLATE_STR).unwrap());
-    spinner.enable_steady_tick(Duration::from_secs_f32(crate::static_const::FPS));
+  /// Limit the number of parallel `make` jobs
+  pub fn with_jobs(mut self, jobs: usize) -> Self {
+    self.jobs = jobs.max(1);
+    self
+  }
+
+  pub fn with_verbose(mut self, verbose: bool) -> Self {
+    self.verbose = verbose;
+    self
+  }
 
-    Self(src.into())
+  pub fn with_mode(mut self, mode: ProgressMode) -> Self {
+    self.mode = mode;
+    self
+  }
+
+  /// The path of PHP source code
+  pub fn src(&self) -> &Path {
+    &self.src
+  }
+
+  /// Work dir this version belongs to, sources live in its `archives/<version>`
+  pub fn work_dir(&self) -> &Path {
+    self.src.parent().and_then(Path::parent).unwrap_or(&self.src)
+  }
+
+  /// `bin` link of the work dir, pointing at the active version
+  fn bin_link(&self) -> PathBuf {
+    self.work_dir().join("bin")
   }
 
   pub fn is_installed(&self) -> bool {
-    self.0.join("dist/bin/php").is_file()
+    self.src.join("dist/bin/php").is_file()
+  }
+
+  /// Mark `src` (created when missing) as being built or installed by this process until the lock is dropped
+  pub fn lock(&self) -> Maybe<BuildLock> {
+    std::fs::create_dir_all(&self.src)?;
+    let path = self.src.join(BuildLock::FILE);
+    std::fs::write(&path, std::process::id().to_string())?;
+    Ok(BuildLock(path))
+  }
+
+  /// Pid of the process building or installing this version, if it's still running
+  pub fn locked_by(&self) -> Option<i32> {
+    let pid = std::fs::read_to_string(self.src.join(BuildLock::FILE)).ok()?;
+    pid.trim().parse().ok().filter(|pid| crate::fpm::is_running(*pid))
   }
 
+  /// Compile and install into `dist`, the options are recorded in the build manifest.
+  ///
   /// # Return
   /// dist pathbuf
-  pub async fn install(&self) -> Maybe<PathBuf> {
-    if self.is_installed() && !CLI.command.is_force() {
-      return Ok(self.0.join("dist"));
-    }
-
+  pub async fn install(&self, options: &BuildOptions) -> Maybe<PathBuf> {
     self.build_conf().await?;
-    self.configure().await?;
+    self.configure(options).await?;
     self.make_install().await?;
 
-    Ok(self.0.join("dist"))
+    let manifest = BuildManifest {
+      version: self.name().into_owned(),
+      options: options.clone(),
+      built_at: crate::date::unix_now(),
+    };
+    manifest.save(&self.src)?;
+
+    let fpm = crate::fpm::Fpm::new(self);
+    if fpm.is_available() {
+      fpm.write_config()?;
+    }
+
+    Ok(self.src.join("dist"))
+  }
+
+  /// Options this version was built with, from the manifest or recovered from `php-config`
+  pub fn build_options(&self) -> Option<BuildOptions> {
+    if let Some(manifest) = BuildManifest::load(&self.src) {
+      return Some(manifest.options);
+    }
+
+    let php_config = std::fs::read_to_string(self.dist().join("bin/php-config")).ok()?;
+    BuildOptions::from_php_config(&php_config)
   }
 
   async fn build_conf(&self) -> Maybe<()> {
     let mut build_conf = Command::new("sh");
-    let cmd = build_conf.arg(self.0.join("buildconf")).arg("--force").current_dir(&self.0);
+    let cmd = build_conf.arg(self.src.join("buildconf")).arg("--force").current_dir(&self.src);
 
     self.run_with_spinner("sh buildconnf --force", cmd).await?;
 
     Ok(())
   }
 
-  fn get_args(&self) -> Vec<String> {
-    macro_rules! emit {
-      ($ident:ident, $a:ident $(,)?) => {
-        if $ident {
-          $a.push(::core::concat!("--", ::core::stringify!($ident)).replace("_", "-"));
-        }
-      };
-      (PathBuf($value:ident), $vec:ident $(,)?) => {
-        if $value.as_os_str() == "default" {
-          $vec.push(::core::concat!("--", ::core::stringify!($value)).replace("_", "-"));
-        } else if !$value.as_os_str().is_empty() {
-          $vec.push(::core::concat!("--", ::core::stringify!($value), "=").replace("_", "-") + $value.as_os_str().to_str().unwrap());
-        }
-      };
-    }
-
-    let Commands::Install {
-      enable_calendar,
-      enable_intl,
-      enable_mbstring,
-      enable_pcntl,
-      enable_bcmath,
-      enable_mysqlnd,
-      with_curl,
-      with_openssl,
-      with_pear,
-      with_zip,
-      with_zlib,
-      with_password_argon2,
-      ref with_mysqli,
-      ref with_pdo_mysqli,
-      ref with_pgsql,
-      ref with_pdo_pgsql,
-      ref configure_args,
-      ..
-    } = CLI.command
-    else {
-      unreachable!("Unreachable code!")
-    };
-
-    let mut args = vec![];
-    emit!(enable_calendar, args);
-    emit!(enable_intl, args);
-    emit!(enable_mbstring, args);
-    emit!(enable_pcntl, args);
-    emit!(enable_bcmath, args);
-    emit!(enable_mysqlnd, args);
-    emit!(with_curl, args);
-    emit!(with_openssl, args);
-    emit!(with_pear, args);
-    emit!(with_zip, args);
-    emit!(with_zlib, args);
-    emit!(with_password_argon2, args);
-
-    emit!(PathBuf(with_mysqli), args);
-    emit!(PathBuf(with_pdo_mysqli), args);
-    emit!(PathBuf(with_pgsql), args);
-    emit!(PathBuf(with_pdo_pgsql), args);
-
-    args.push(configure_args.join(" "));
-    args
-  }
-
-  async fn configure(&self) -> Maybe<()> {
+  async fn configure(&self, options: &BuildOptions) -> Maybe<()> {
     let mut configure = Command::new("./configure");
-    let args = self.get_args();
-    let cmd = configure.arg("--prefix").arg(self.0.join("dist")).args(&args).current_dir(&self.0);
-    let cmd = if !CLI.command.is_dev() { cmd } else { cmd.arg("--enable-debug") };
+    let args = &options.configure_args;
+    let cmd = configure.arg("--prefix").arg(self.src.join("dist")).args(args).current_dir(&self.src);
+    let cmd = if !options.debug { cmd } else { cmd.arg("--enable-debug") };
+    // Snippets of `maphp xdebug` and `maphp opcache` live in conf.d
+    if args.iter().all(|arg| !arg.starts_with("--with-config-file-scan-dir")) {
+      cmd.arg(format!("--with-config-file-scan-dir={}", self.src.join("dist/lib/conf.d").display()));
+    }
 
     let prefix = format!(
       "./configure --prefix {dist} {debug}{args}",
-      dist = self.0.join("dist").display(),
-      debug = if CLI.command.is_dev() { "--enable-debug " } else { " " },
+      dist = self.src.join("dist").display(),
+      debug = if options.debug { "--enable-debug " } else { " " },
       args = args.join(" "),
     );
 
@@ -144,9 +159,9 @@ impl SourcePHP {
   }
 
   async fn make_install(&self) -> Maybe<()> {
-    let cpus = num_cpus::get();
+    let cpus = self.jobs;
     let mut make = Command::new("make");
-    let cmd = make.arg("install").arg(format!("-j{cpus}")).current_dir(&self.0);
+    let cmd = make.arg("install").arg(format!("-j{cpus}")).current_dir(&self.src);
 
     let analogy = format!("make install with {cpus} job(s)");
     self.run_with_spinner(analogy, cmd).await?;
@@ -154,55 +169,77 @@ impl SourcePHP {
     Ok(())
   }
 
-  pub async fn setup_ini(&self) -> Maybe<()> {
-    println!("Setting up php.ini");
-    if self.0.join("dist/lib/php.ini").is_file() {
-      println!("✅ php.ini already exists, skipping");
-      return Ok(());
+  /// Copy the `php.ini` template matching the profile into `dist/lib`, unless there already is one.
+  ///
+  /// Returns a warning when the template is missing and the build is left without php.ini.
+  pub async fn setup_ini(&self, debug: bool) -> Maybe<Option<String>> {
+    if self.src.join("dist/lib/php.ini").is_file() {
+      return Ok(None);
+    }
+
+    let template = if debug { "php.ini-development" } else { "php.ini-production" };
+    let php_ini = self.src.join(template);
+    if !php_ini.is_file() {
+      return Ok(Some(format!("{template} not found, no php.ini was set up")));
     }
 
-    let php_devel = self.0.join("php.ini-development");
-    let php_prods = self.0.join("php.ini-production");
+    tokio::fs::copy(php_ini, self.src.join("dist/lib/php.ini")).await?;
 
-    let php_ini = if CLI.command.is_dev() {
-      if !php_devel.is_file() {
-        println!("⚠️ php.ini-development not found, skipping");
-        return Ok(());
-      } else {
-        php_devel
-      }
-    } else if !php_prods.is_file() {
-      println!("⚠️ php.ini-production not found, skipping");
-      return Ok(());
-    } else {
-      php_prods
-    };
+    Ok(None)
+  }
 
-    tokio::fs::copy(php_ini, self.0.join("dist/lib/php.ini")).await?;
+  /// Install a PECL extension with this version's `pecl`, prompts get their default answer
+  pub async fn pecl_install(&self, extension: &str) -> Maybe<()> {
+    let mut pecl = Command::new(self.dist().join("bin/pecl"));
+    let cmd = pecl.arg("install").arg("-f").arg(extension).current_dir(&self.src);
 
-    Ok(())
+    self.run_with_spinner(format!("pecl install {extension}"), cmd).await
   }
 
   pub async fn link(&self) -> Maybe<()> {
-    if CLI.bin().exists() {
-      tokio::fs::remove_dir_all(CLI.bin()).await?;
+    ensure!(
+      !self.is_broken(),
+      "PHP {} failed its post-install verification, reinstall it with --force",
+      self.name()
+    );
+
+    // `symlink_metadata` so a link left dangling by a removed version is replaced too
+    let bin = self.bin_link();
+    match tokio::fs::symlink_metadata(&bin).await {
+      Ok(meta) if meta.is_symlink() => tokio::fs::remove_file(&bin).await?,
+      Ok(_) => tokio::fs::remove_dir_all(&bin).await?,
+      Err(_) => {}
     }
 
     #[cfg(unix)]
-    tokio::fs::symlink(self.0.join("dist/bin"), CLI.bin()).await?;
+    tokio::fs::symlink(self.src.join("dist/bin"), bin).await?;
 
     Ok(())
   }
 
   async fn run_with_spinner(&self, analogy: impl ToString, command: &mut Command) -> Maybe<()> {
-    let spinner = Self::new_spinner();
-    spinner.set_prefix(analogy.to_string());
+    let spinner = match self.progress {
+      Some(ref progress) => {
+        progress.set_prefix(format!("{}: {}", self.name(), analogy.to_string()));
+        progress.clone()
+      }
+      None => {
+        let spinner = Reporter::spinner(self.mode);
+        spinner.set_prefix(analogy.to_string());
+        spinner
+      }
+    };
 
-    let mut child = command.stdout(Stdio::piped()).stderr(Stdio::piped()).kill_on_drop(true).spawn()?;
+    let mut child = command
+      .stdin(Stdio::null())
+      .stdout(Stdio::piped())
+      .stderr(Stdio::piped())
+      .kill_on_drop(true)
+      .spawn()?;
     let stdout = child.stdout.take().expect("Unexpected STDIO piped stdout not found");
     let stderr = child.stderr.take().expect("Unexpected STDIO piped stdout not found");
 
-    let verbose = CLI.command.is_verbose();
+    let verbose = self.verbose;
     let progress = spinner.clone();
     let stdout_handle = tokio::spawn(async move {
       let mut lines = BufReader::new(stdout).lines();
@@ -230,16 +267,23 @@ impl SourcePHP {
     stderr_handle.await?;
 
     ensure!(status.success());
+    if self.progress.is_none() {
+      spinner.finish();
+    }
 
     Ok(())
   }
 
   pub fn name(&self) -> Cow<'_, str> {
     self
-      .0
+      .src
       .file_name()
       .map(std::ffi::OsStr::to_string_lossy)
-      .unwrap_or_else(|| self.0.to_string_lossy())
+      .unwrap_or_else(|| self.src.to_string_lossy())
+  }
+
+  pub fn version(&self) -> Option<Version> {
+    self.name().parse().ok()
   }
 
   pub fn details(&self) -> String {
@@ -251,7 +295,7 @@ impl SourcePHP {
       .arg("-v")
       .stderr(Stdio::null())
       .stdout(Stdio::piped())
-      .current_dir(self.0.join("dist/bin"))
+      .current_dir(self.src.join("dist/bin"))
       .output();
 
     let stdout = match command {
@@ -269,14 +313,25 @@ impl SourcePHP {
     }
   }
 
+  /// Whether this is the version configured with `maphp default`
+  pub fn is_default(&self) -> bool {
+    Config::load(Config::path(self.work_dir())).is_ok_and(|config| config.default.as_deref() == Some(&self.name()))
+  }
+
+  /// Whether the post-install verification of this version failed
+  pub fn is_broken(&self) -> bool {
+    crate::verify::Verification::load(&self.src).is_some_and(|verification| !verification.is_ok())
+  }
+
   pub fn is_in_path(&self) -> bool {
-    let Ok(realpath) = dunce::realpath(CLI.bin()) else { return false };
-    self.0.join("dist/bin") == realpath
+    let Ok(realpath) = dunce::realpath(self.bin_link()) else { return false };
+    self.src.join("dist/bin") == realpath
   }
 
-  pub fn scan_local() -> Maybe<Vec<Self>> {
+  /// Installed versions in an `archives` directory
+  pub fn scan_local(archives: impl AsRef<Path>) -> Maybe<Vec<Self>> {
     Ok(
-      std::fs::read_dir(CLI.archives())?
+      std::fs::read_dir(archives)?
         .flatten()
         .filter_map(|d| {
           let source = Self::new(d.path());
@@ -287,8 +342,164 @@ impl SourcePHP {
   }
 }
 
+/// Build or install in progress, see [`SourcePHP::lock`]
+#[derive(Debug)]
+pub struct BuildLock(PathBuf);
+
+impl BuildLock {
+  const FILE: &str = ".maphp.lock";
+}
+
+impl Drop for BuildLock {
+  fn drop(&mut self) {
+    _ = std::fs::remove_file(&self.0);
+  }
+}
+
+/// Machine readable description of a version, used by `list`, `info` and `current`
+#[derive(Debug, Clone, Serialize)]
+pub struct VersionInfo {
+  pub version: String,
+  pub stability: Stability,
+  pub installed: bool,
+  /// `php` on the `bin` link resolves to this version
+  pub active: bool,
+  /// Configured with `maphp default`
+  pub default: bool,
+  /// Failed its post-install verification
+  pub broken: bool,
+  /// `release`, `debug`, `zts` or `debug-zts`
+  pub variant: Option<String>,
+  pub path: Option<PathBuf>,
+  /// RFC 3339 timestamp of the compiled binary
+  pub installed_at: Option<String>,
+  /// Size in bytes of the `dist` directory
+  pub size: Option<u64>,
+  /// Release date (`YYYY-MM-DD`) from the release index
+  pub released_at: Option<String>,
+  /// Support status of the minor series from the release index
+  pub support: Option<Support>,
+}
+
+impl VersionInfo {
+  /// Info of a version which isn't installed locally
+  pub fn remote(version: impl Into<String>) -> Self {
+    let version = version.into();
+    Self {
+      stability: Stability::of(&version),
+      version,
+      installed: false,
+      active: false,
+      default: false,
+      broken: false,
+      variant: None,
+      path: None,
+      installed_at: None,
+      size: None,
+      released_at: None,
+      support: None,
+    }
+  }
+
+  /// Fill release date and support status from the release index
+  pub fn with_release(mut self, index: Option<&ReleaseIndex>) -> Self {
+    if let (Some(index), Ok(version)) = (index, self.version.parse::<Version>()) {
+      self.released_at = index.release_date(&version).map(str::to_owned);
+      self.support = index.support(&version);
+    }
+    self
+  }
+}
+
+impl SourcePHP {
+  pub fn dist(&self) -> PathBuf {
+    self.src.join("dist")
+  }
+
+  /// Directory shared extensions are loaded from, as reported by `php-config --extension-dir`
+  pub fn extension_dir(&self) -> Option<PathBuf> {
+    let output = std::process::Command::new(self.dist().join("bin/php-config"))
+      .arg("--extension-dir")
+      .stderr(Stdio::null())
+      .output()
+      .ok()?;
+    let dir = String::from_utf8(output.stdout).ok()?;
+
+    output.status.success().then(|| PathBuf::from(dir.trim()))
+  }
+
+  /// Names of the shared extensions (`*.so`) in the extension directory
+  pub fn shared_extensions(&self) -> Vec<String> {
+    let Some(Ok(entries)) = self.extension_dir().map(std::fs::read_dir) else {
+      return vec![];
+    };
+
+    let mut extensions = entries
+      .flatten()
+      .map(|entry| entry.path())
+      .filter(|path| path.extension().is_some_and(|ext| ext == "so"))
+      .filter_map(|path| Some(path.file_stem()?.to_string_lossy().into_owned()))
+      .collect::<Vec<_>>();
+    extensions.sort();
+    extensions
+  }
+
+  /// Build variant read from the configure options recorded in `php-config`
+  pub fn variant(&self) -> Option<String> {
+    let php_config = std::fs::read_to_string(self.dist().join("bin/php-config")).ok()?;
+    let options = php_config.lines().find(|line| line.starts_with("configure_options="))?;
+    let debug = options.contains("--enable-debug");
+    let zts = options.contains("--enable-zts") || options.contains("--enable-maintainer-zts");
+
+    let variant = match (debug, zts) {
+      (false, false) => "release",
+      (true, false) => "debug",
+      (false, true) => "zts",
+      (true, true) => "debug-zts",
+    };
+    Some(variant.to_owned())
+  }
+
+  pub fn info(&self) -> VersionInfo {
+    let binary = self.dist().join("bin/php");
+    let installed_at = std::fs::metadata(&binary)
+      .and_then(|meta| meta.created().or_else(|_| meta.modified()))
+      .ok()
+      .map(crate::date::format_system_time);
+    let installed = self.is_installed();
+
+    VersionInfo {
+      version: self.name().into_owned(),
+      stability: Stability::of(&self.name()),
+      installed,
+      active: self.is_in_path(),
+      default: self.is_default(),
+      broken: self.is_broken(),
+      variant: self.variant(),
+      path: Some(self.src.clone()),
+      installed_at,
+      size: installed.then(|| crate::disk::dir_size(self.dist())),
+      released_at: None,
+      support: None,
+    }
+  }
+}
+
 impl Display for SourcePHP {
   fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
     f.write_str(self.name().as_ref())
   }
 }
+
+#[test]
+fn test_lock() {
+  let source = SourcePHP::new(std::env::temp_dir().join(format!("maphp-lock-{}/archives/8.3.0", std::process::id())));
+  assert_eq!(source.locked_by(), None);
+
+  let lock = source.lock().unwrap();
+  assert_eq!(source.locked_by(), Some(std::process::id() as i32));
+  drop(lock);
+  assert_eq!(source.locked_by(), None);
+
+  std::fs::remove_dir_all(source.work_dir()).unwrap();
+}
@@ -0,0 +1,103 @@
+use crate::Maybe;
+use crate::stats::Tag;
+use anyhow::{Context, bail};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashSet};
+use std::path::Path;
+use std::time::Duration;
+
+/// Current format of `tags.json`, bump it together with a new arm in [`TagCache::migrate`] whenever [`Tag`] changes.
+pub const SCHEMA: u64 = 1;
+
+/// Local copy of the upstream tags
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct TagCache {
+  pub schema: u64,
+  /// Unix seconds of the last complete refresh, `0` when never refreshed
+  pub fetched_at: u64,
+  /// `ETag` of each tags page, unchanged pages are skipped on refresh
+  #[serde(default)]
+  pub etags: BTreeMap<u64, String>,
+  pub tags: HashSet<Tag>,
+}
+
+impl TagCache {
+  /// Load the cache at `path`, older formats are migrated.
+  ///
+  /// Returns `Ok(None)` when there is no cache yet and an error when it is unreadable.
+  pub fn load(path: impl AsRef<Path>) -> Maybe<Option<Self>> {
+    let content = match std::fs::read(path.as_ref()) {
+      Ok(content) => content,
+      Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+      Err(err) => return Err(err.into()),
+    };
+
+    let value = serde_json::from_slice(&content).with_context(|| format!("{} is corrupted", path.as_ref().display()))?;
+    Self::migrate(value).map(Some)
+  }
+
+  fn migrate(value: Value) -> Maybe<Self> {
+    let schema = match value {
+      // Before versioning the file was a bare set of tags
+      Value::Array(_) => 0,
+      ref value => value.get("schema").and_then(Value::as_u64).context("Tag cache has no schema")?,
+    };
+
+    match schema {
+      0 => Ok(Self {
+        schema: SCHEMA,
+        fetched_at: 0,
+        etags: BTreeMap::new(),
+        tags: serde_json::from_value(value)?,
+      }),
+      SCHEMA => Ok(serde_json::from_value(value)?),
+      _ => bail!("Tag cache schema {schema} is newer than supported ({SCHEMA}), upgrade maphp"),
+    }
+  }
+
+  /// Write the cache to a temporary file and rename it over `path`, a crash never leaves a partial file.
+  pub fn save(&self, path: impl AsRef<Path>) -> Maybe<()> {
+    crate::disk::atomic_write(path, serde_json::to_vec(self)?)
+  }
+
+  /// Whether the last complete refresh is older than `ttl`, a zero `ttl` never expires
+  pub fn is_stale(&self, ttl: Duration) -> bool {
+    self.fetched_at == 0 || !ttl.is_zero() && crate::date::unix_now().saturating_sub(self.fetched_at) > ttl.as_secs()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn tag(name: &str) -> Value {
+    let url = "https://api.github.com/repos/php/php-src/zipball/refs/tags/x";
+    serde_json::json!({ "name": name, "tarball_url": url, "zipball_url": url, "commit": { "sha": "a", "url": url }, "node_id": "n" })
+  }
+
+  #[test]
+  fn test_migrate_and_save() {
+    let dir = std::env::temp_dir().join(format!("maphp-cache-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("tags.json");
+
+    std::fs::write(&path, Value::Array(vec![tag("php-8.3.13"), tag("php-8.4.0RC1")]).to_string()).unwrap();
+    let mut cache = TagCache::load(&path).unwrap().unwrap();
+    assert_eq!(cache.schema, SCHEMA);
+    assert_eq!(cache.tags.len(), 2);
+    assert!(cache.is_stale(Duration::from_secs(3600)));
+
+    cache.fetched_at = crate::date::unix_now();
+    cache.save(&path).unwrap();
+    let cache = TagCache::load(&path).unwrap().unwrap();
+    assert!(!cache.is_stale(Duration::from_secs(3600)));
+    assert!(!dir.join("tags.json.tmp").exists());
+
+    std::fs::write(&path, "[{\"name\":").unwrap();
+    assert!(TagCache::load(&path).is_err());
+    assert!(TagCache::load(dir.join("missing.json")).unwrap().is_none());
+
+    std::fs::remove_dir_all(dir).unwrap();
+  }
+}
@@ -1,11 +1,17 @@
-use crate::Maybe;
-use crate::static_const::TAG_HEADERS;
-use reqwest::Url;
+use crate::{Maybe, github};
+use reqwest::header::{ETAG, IF_NONE_MATCH};
+use reqwest::{StatusCode, Url};
 use serde::{Deserialize, Serialize};
 use std::num::NonZeroU64;
 use std::sync::Arc;
 
+pub mod cache;
 mod imp;
+pub mod releases;
+mod version;
+
+pub use version::Version;
+
 #[derive(Debug, Clone, Hash, PartialOrd, PartialEq, Ord, Eq, Deserialize, Serialize)]
 #[non_exhaustive]
 pub struct Tag {
@@ -25,20 +31,61 @@ impl Tag {
     }
   }
 
+  pub fn version(&self) -> Option<Version> {
+    self.as_semver().parse().ok()
+  }
+
+  pub fn stability(&self) -> Stability {
+    Stability::of(&self.name)
+  }
+
   pub fn is_alpha(&self) -> bool {
-    self.name.contains("alpha") || self.name.contains("ALPHA")
+    self.stability() == Stability::Alpha
   }
 
   pub fn is_beta(&self) -> bool {
-    self.name.contains("beta") || self.name.contains("BETA")
+    self.stability() == Stability::Beta
   }
 
   pub fn is_rc(&self) -> bool {
-    self.name.contains("RC") || self.name.contains("rc")
+    self.stability() == Stability::Rc
   }
 
   pub fn is_stable(&self) -> bool {
-    !self.is_beta() && !self.is_alpha() && !self.is_rc()
+    self.stability() == Stability::Stable
+  }
+}
+
+#[derive(Debug, Clone, Copy, Hash, PartialOrd, PartialEq, Ord, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Stability {
+  Alpha,
+  Beta,
+  Rc,
+  Stable,
+}
+
+impl Stability {
+  /// Stability of a tag or version name such as `php-8.4.0RC1`
+  pub fn of(name: &str) -> Self {
+    if name.contains("alpha") || name.contains("ALPHA") {
+      Self::Alpha
+    } else if name.contains("beta") || name.contains("BETA") {
+      Self::Beta
+    } else if name.contains("RC") || name.contains("rc") {
+      Self::Rc
+    } else {
+      Self::Stable
+    }
+  }
+
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      Self::Alpha => "alpha",
+      Self::Beta => "beta",
+      Self::Rc => "rc",
+      Self::Stable => "stable",
+    }
   }
 }
 
@@ -48,6 +95,54 @@ pub struct Commit {
   url: Url,
 }
 
+/// One page of the tags API
+#[derive(Debug)]
+pub enum TagPage {
+  /// Tags of the page with the `ETag` to send on the next refresh
+  Tags { tags: Vec<Tag>, etag: Option<String> },
+  /// The page didn't change since `etag` was received
+  NotModified,
+  /// Past the last page
+  End,
+}
+
+/// Fetch a single page of tags from the official repo.
+///
+/// When `etag` is given the request is conditional, unchanged pages don't count against the rate limit.
+///
+/// # Errors
+///
+/// Returns error if the network fails, the rate limit is exhausted or the response is invalid.
+pub async fn fetch_tags(client: &reqwest::Client, page: NonZeroU64, etag: Option<&str>) -> Maybe<TagPage> {
+  let url = "https://api.github.com/repos/php/php-src/tags";
+  let query = [("page", page.to_string()), ("per_page", 100.to_string())];
+  let mut request = client.get(url).query(&query);
+  if let Some(etag) = etag {
+    request = request.header(IF_NONE_MATCH, etag);
+  }
+
+  let response = github::send(request).await?;
+  match response.status() {
+    StatusCode::NOT_FOUND => return Ok(TagPage::End),
+    StatusCode::NOT_MODIFIED => return Ok(TagPage::NotModified),
+    _ => {}
+  }
+
+  let response = response.error_for_status()?;
+  let etag = response.headers().get(ETAG).and_then(|v| v.to_str().ok()).map(str::to_owned);
+  let tags: Vec<_> = response
+    .json::<Vec<Tag>>()
+    .await?
+    .into_iter()
+    .filter_map(|tag| tag.name.starts_with("php-").then_some(tag))
+    .collect();
+
+  match tags.len().gt(&1) {
+    true => Ok(TagPage::Tags { tags, etag }),
+    false => Ok(TagPage::End),
+  }
+}
+
 /// Fetch tags from official repo and return sets of the tags.
 ///
 /// Returns `None` if page doesn't exist.
@@ -60,18 +155,13 @@ pub struct Commit {
 ///
 /// * [`Ok(Some(HashSet<Tag>))`] - If there was some tags
 /// * [`Ok(None)`] - if 404 or non tags
-/// * [`Err(reqwest::Error)`] - otherwise reqwest errors
-///
-/// # Errors
-///
-/// Returns error if this was the case:
-/// - Network connection gagal
-/// - Request timeout
-/// - Invalid response dari server
+/// * [`Err(anyhow::Error)`] - otherwise network or rate limit errors
 ///
 /// # Examples
 ///
 /// ```no_run
+/// # async fn run() -> maphp::Maybe<()> {
+/// use maphp::stats::get_tags;
 /// use std::num::NonZero;
 ///
 /// let page = NonZero::new(1).unwrap();
@@ -79,25 +169,14 @@ pub struct Commit {
 ///     Some(tags) => println!("Found {} tags", tags.len()),
 ///     None => println!("Page not found"),
 /// }
+/// # Ok(())
+/// # }
 /// ```
-pub async fn get_tags(page: NonZeroU64) -> Maybe<Option<Vec<Tag>>, reqwest::Error> {
-  let url = "https://api.github.com/repos/php/php-src/tags";
-  let query = [("page", page.to_string()), ("per_page", 100.to_string())];
-  let client = reqwest::Client::builder().default_headers(TAG_HEADERS.clone()).build()?;
-  let response = client.get(url).query(&query).send().await?;
-
-  if response.status().as_u16() == 404 {
-    return Ok(None);
+pub async fn get_tags(page: NonZeroU64) -> Maybe<Option<Vec<Tag>>> {
+  match fetch_tags(&github::client()?, page, None).await? {
+    TagPage::Tags { tags, .. } => Ok(Some(tags)),
+    TagPage::NotModified | TagPage::End => Ok(None),
   }
-
-  let tags: Vec<_> = response
-    .json::<Vec<Tag>>()
-    .await?
-    .into_iter()
-    .filter_map(|tag| tag.name.starts_with("php-").then_some(tag))
-    .collect();
-
-  Ok(tags.len().gt(&1).then_some(tags))
 }
 
 #[tokio::test]
@@ -0,0 +1,206 @@
+use crate::Maybe;
+use crate::stats::Version;
+use anyhow::Context;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::Duration;
+
+/// Support status of a minor series
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Support {
+  /// Bug and security fixes
+  Active,
+  /// Security fixes only
+  Security,
+  /// End of life, no fixes at all
+  Eol,
+}
+
+impl Support {
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      Self::Active => "active",
+      Self::Security => "security",
+      Self::Eol => "eol",
+    }
+  }
+}
+
+/// A minor series as published by php.net `releases/branches.php`
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Branch {
+  pub branch: String,
+  #[serde(default)]
+  pub latest: Option<String>,
+  #[serde(default)]
+  pub state: Option<String>,
+  #[serde(default)]
+  pub initial_release: Option<String>,
+  #[serde(default)]
+  pub active_support_end: Option<String>,
+  #[serde(default)]
+  pub security_support_end: Option<String>,
+}
+
+impl Branch {
+  fn series(&self) -> Option<(u64, u64)> {
+    self.branch.parse::<Version>().ok().map(|v| v.series())
+  }
+
+  /// Date part (`YYYY-MM-DD`) of the end of security support
+  pub fn eol_date(&self) -> Option<&str> {
+    self.security_support_end.as_deref().and_then(|d| d.get(..10))
+  }
+
+  /// Support status as of `today` (`YYYY-MM-DD`), dates win over the published state which may be stale
+  pub fn support(&self, today: &str) -> Support {
+    let passed = |date: &Option<String>| date.as_deref().and_then(|d| d.get(..10)).is_some_and(|d| d < today);
+
+    if passed(&self.security_support_end) || self.state.as_deref() == Some("eol") {
+      Support::Eol
+    } else if passed(&self.active_support_end) || self.state.as_deref() == Some("security") {
+      Support::Security
+    } else {
+      Support::Active
+    }
+  }
+}
+
+/// Release dates and support windows built from php.net metadata
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct ReleaseIndex {
+  /// Unix seconds of the last refresh
+  pub fetched_at: u64,
+  pub branches: Vec<Branch>,
+  /// Release date (`YYYY-MM-DD`) by version
+  pub releases: BTreeMap<String, String>,
+}
+
+#[derive(Deserialize)]
+struct Release {
+  #[serde(default)]
+  date: Option<String>,
+}
+
+impl ReleaseIndex {
+  pub fn load(path: impl AsRef<Path>) -> Option<Self> {
+    let reader = std::fs::File::open(path).ok()?;
+    serde_json::from_reader(reader).ok()
+  }
+
+  pub fn save(&self, path: impl AsRef<Path>) -> Maybe<()> {
+    crate::disk::atomic_write(path, serde_json::to_vec(self)?)
+  }
+
+  /// Whether the last refresh is older than `ttl`, a zero `ttl` never expires
+  pub fn is_stale(&self, ttl: Duration) -> bool {
+    self.fetched_at == 0 || !ttl.is_zero() && crate::date::unix_now().saturating_sub(self.fetched_at) > ttl.as_secs()
+  }
+
+  /// Fetch branches and releases from `base`, e.g. `https://www.php.net/releases/`
+  pub async fn fetch(base: &Url) -> Maybe<Self> {
+    let client = reqwest::Client::builder().user_agent(crate::static_const::USER_AGENT).build()?;
+
+    let url = base.join("branches.php")?;
+    let branches: Vec<Branch> = client
+      .get(url.clone())
+      .send()
+      .await?
+      .error_for_status()?
+      .json()
+      .await
+      .with_context(|| format!("Invalid branch metadata from {url}"))?;
+
+    let mut majors = branches.iter().filter_map(Branch::series).map(|(major, _)| major).collect::<Vec<_>>();
+    majors.sort_unstable();
+    majors.dedup();
+
+    let mut releases = BTreeMap::new();
+    for major in majors {
+      let mut url = base.join("index.php")?;
+      url.set_query(Some(&format!("json&max=-1&version={major}")));
+      let response: BTreeMap<String, Release> = client
+        .get(url.clone())
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await
+        .with_context(|| format!("Invalid release metadata from {url}"))?;
+
+      releases.extend(
+        response
+          .into_iter()
+          .filter_map(|(version, release)| Some((version, parse_release_date(&release.date?)?))),
+      );
+    }
+
+    Ok(Self {
+      fetched_at: crate::date::unix_now(),
+      branches,
+      releases,
+    })
+  }
+
+  pub fn branch(&self, version: &Version) -> Option<&Branch> {
+    self.branches.iter().find(|branch| branch.series() == Some(version.series()))
+  }
+
+  /// Support status of the series of `version`, series older than every known branch are end of life
+  pub fn support(&self, version: &Version) -> Option<Support> {
+    if let Some(branch) = self.branch(version) {
+      return Some(branch.support(&crate::date::today()));
+    }
+
+    let oldest = self.branches.iter().filter_map(Branch::series).min()?;
+    (version.series() < oldest).then_some(Support::Eol)
+  }
+
+  pub fn release_date(&self, version: &Version) -> Option<&str> {
+    self.releases.get(&version.to_string()).map(String::as_str)
+  }
+}
+
+/// Convert php.net release dates (`24 Oct 2024`) to `YYYY-MM-DD`
+fn parse_release_date(date: &str) -> Option<String> {
+  const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+
+  let mut parts = date.split_whitespace();
+  let day = parts.next()?.parse::<u32>().ok()?;
+  let month = parts.next()?;
+  let month = MONTHS.iter().position(|m| month.starts_with(m))? + 1;
+  let year = parts.next()?.parse::<u32>().ok()?;
+
+  Some(format!("{year:04}-{month:02}-{day:02}"))
+}
+
+#[test]
+fn test_support() {
+  let branch: Branch = serde_json::from_str(
+    r#"{"branch":"8.1","latest":"8.1.31","state":"security","initial_release":"2021-11-25T00:00:00+00:00",
+    "active_support_end":"2023-11-25T00:00:00+00:00","security_support_end":"2025-12-31T00:00:00+00:00"}"#,
+  )
+  .unwrap();
+
+  assert_eq!(branch.support("2024-06-01"), Support::Security);
+  assert_eq!(branch.support("2026-01-01"), Support::Eol);
+  assert_eq!(branch.eol_date(), Some("2025-12-31"));
+  assert_eq!(parse_release_date("24 Oct 2024").as_deref(), Some("2024-10-24"));
+}
+
+#[test]
+fn test_is_stale() {
+  let hour = Duration::from_secs(3600);
+  let mut index = ReleaseIndex::default();
+  assert!(index.is_stale(hour));
+
+  index.fetched_at = crate::date::unix_now() - 7200;
+  assert!(index.is_stale(hour));
+  assert!(!index.is_stale(Duration::ZERO));
+
+  index.fetched_at = crate::date::unix_now();
+  assert!(!index.is_stale(hour));
+}
@@ -0,0 +1,91 @@
+use crate::stats::Stability;
+use std::cmp::Ordering;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+/// PHP version parsed from a tag name such as `php-8.4.0RC1`
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub struct Version {
+  pub major: u64,
+  pub minor: u64,
+  pub patch: u64,
+  pub stability: Stability,
+  /// Pre-release number, `1` for `RC1`
+  pub pre: u64,
+}
+
+impl Version {
+  /// The minor series, e.g. `(8, 3)` for `8.3.13`
+  pub fn series(&self) -> (u64, u64) {
+    (self.major, self.minor)
+  }
+}
+
+impl FromStr for Version {
+  type Err = String;
+
+  fn from_str(value: &str) -> Result<Self, Self::Err> {
+    let value = value.trim();
+    let value = value.strip_prefix("php-").unwrap_or(value);
+    let split = value.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(value.len());
+    let (numbers, suffix) = value.split_at(split);
+
+    let mut numbers = numbers.trim_end_matches('.').split('.').map(str::parse::<u64>);
+    let mut next = |required: bool| match numbers.next() {
+      Some(Ok(n)) => Ok(n),
+      None if !required => Ok(0),
+      _ => Err(format!("Invalid version `{value}`")),
+    };
+    let (major, minor, patch) = (next(true)?, next(false)?, next(false)?);
+
+    let suffix = suffix.trim_start_matches(['-', '_', '.']);
+    let number_at = suffix.len() - suffix.trim_start_matches(|c: char| !c.is_ascii_digit()).len();
+    let pre = suffix[number_at..].parse().unwrap_or_default();
+
+    Ok(Self {
+      major,
+      minor,
+      patch,
+      stability: Stability::of(suffix),
+      pre,
+    })
+  }
+}
+
+impl Ord for Version {
+  fn cmp(&self, other: &Self) -> Ordering {
+    (self.major, self.minor, self.patch, self.stability, self.pre).cmp(&(other.major, other.minor, other.patch, other.stability, other.pre))
+  }
+}
+
+impl PartialOrd for Version {
+  fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+    Some(self.cmp(other))
+  }
+}
+
+impl Display for Version {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{}.{}.{}", self.major, self.minor, self.patch)?;
+    match self.stability {
+      Stability::Alpha => write!(f, "alpha{}", self.pre),
+      Stability::Beta => write!(f, "beta{}", self.pre),
+      Stability::Rc => write!(f, "RC{}", self.pre),
+      Stability::Stable => Ok(()),
+    }
+  }
+}
+
+#[test]
+fn test_version_order() {
+  let parse = |v: &str| v.parse::<Version>().unwrap();
+
+  assert_eq!(parse("php-8.4.0RC1").to_string(), "8.4.0RC1");
+  assert_eq!(parse("8.3").series(), (8, 3));
+  assert!(parse("8.4.0alpha2") < parse("8.4.0beta1"));
+  assert!(parse("8.4.0beta3") < parse("8.4.0RC1"));
+  assert!(parse("8.4.0RC4") < parse("8.4.0"));
+  assert!(parse("8.3.9") < parse("8.3.13"));
+  assert!(parse("7.4.33") < parse("8.0.0"));
+  assert!("php-next".parse::<Version>().is_err());
+}
@@ -0,0 +1,183 @@
+use crate::Maybe;
+use crate::manifest::BuildOptions;
+use crate::source::SourcePHP;
+use anyhow::bail;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+
+/// Extensions that can be asked for with `--enable-*`/`--with-*`, other flags only tune the build
+const EXTENSIONS: &str = "bcmath bz2 calendar ctype curl dba dom exif ffi fileinfo ftp gd gettext gmp iconv intl ldap mbstring mysqli mysqlnd \
+  opcache openssl pcntl pdo_mysql pdo_pgsql pdo_sqlite pgsql posix readline shmop soap sockets sodium sqlite3 sysvmsg sysvsem sysvshm xsl zip zlib";
+
+/// Outcome of one post-install check
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Check {
+  pub name: String,
+  pub ok: bool,
+  pub detail: String,
+}
+
+/// `verify.json` stored next to `build.json`, a failed verification keeps the version from being linked
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Verification {
+  pub checks: Vec<Check>,
+  /// Unix seconds of the verification
+  pub verified_at: u64,
+}
+
+impl Verification {
+  pub fn path(src: impl AsRef<Path>) -> PathBuf {
+    src.as_ref().join("verify.json")
+  }
+
+  pub fn load(src: impl AsRef<Path>) -> Option<Self> {
+    let reader = std::fs::File::open(Self::path(src)).ok()?;
+    serde_json::from_reader(reader).ok()
+  }
+
+  pub fn save(&self, src: impl AsRef<Path>) -> Maybe<()> {
+    crate::disk::atomic_write(Self::path(src), serde_json::to_vec_pretty(self)?)
+  }
+
+  pub fn is_ok(&self) -> bool {
+    self.checks.iter().all(|check| check.ok)
+  }
+
+  /// Run the new binary: `php -v`, the requested extensions in `php -m` and the ini in `php --ini`
+  pub fn run(source: &SourcePHP, options: &BuildOptions) -> Self {
+    let mut checks = vec![];
+
+    let version = php(source, &["-v"]);
+    let expected = format!("PHP {}", source.name());
+    checks.push(match version {
+      Ok(ref output) if output.starts_with(&expected) => check("php -v", true, output.lines().next().unwrap_or_default()),
+      Ok(ref output) => check(
+        "php -v",
+        false,
+        format!("expected {expected}, got {}", output.lines().next().unwrap_or_default()),
+      ),
+      Err(ref err) => check("php -v", false, err),
+    });
+
+    let modules = php(source, &["-m"]).map(|output| output.lines().map(|line| line.trim().to_lowercase()).collect::<Vec<_>>());
+    checks.push(match modules {
+      Ok(ref modules) => {
+        let missing = expected_modules(&options.configure_args)
+          .into_iter()
+          .filter(|module| !modules.contains(&module_name(module).to_lowercase()))
+          .collect::<Vec<_>>();
+        match missing.is_empty() {
+          true => check("php -m", true, format!("{} modules", modules.len())),
+          false => check("php -m", false, format!("missing {}", missing.join(", "))),
+        }
+      }
+      Err(ref err) => check("php -m", false, err),
+    });
+
+    let php_ini = source.dist().join("lib/php.ini");
+    let ini = php(source, &["--ini"]);
+    checks.push(match ini {
+      Ok(ref output) => {
+        let loaded = output
+          .lines()
+          .find_map(|line| line.strip_prefix("Loaded Configuration File:"))
+          .map(str::trim)
+          .unwrap_or("(none)");
+        // Relocated builds load `bin/php.ini`, a link to the same file
+        let same = |a: &Path, b: &Path| a == b || std::fs::canonicalize(a).is_ok_and(|a| std::fs::canonicalize(b).is_ok_and(|b| a == b));
+        match loaded {
+          "(none)" if !php_ini.exists() => check("php --ini", true, "no php.ini"),
+          loaded if same(Path::new(loaded), &php_ini) => check("php --ini", true, loaded),
+          loaded => check("php --ini", false, format!("expected {}, loaded {loaded}", php_ini.display())),
+        }
+      }
+      Err(ref err) => check("php --ini", false, err),
+    });
+
+    Self {
+      checks,
+      verified_at: crate::date::unix_now(),
+    }
+  }
+}
+
+fn check(name: &str, ok: bool, detail: impl ToString) -> Check {
+  Check {
+    name: name.to_owned(),
+    ok,
+    detail: detail.to_string(),
+  }
+}
+
+fn php(source: &SourcePHP, args: &[&str]) -> Result<String, String> {
+  let output = std::process::Command::new(source.dist().join("bin/php"))
+    .args(args)
+    .stdin(Stdio::null())
+    .output()
+    .map_err(|err| err.to_string())?;
+  match output.status.success() {
+    true => Ok(String::from_utf8_lossy(&output.stdout).into_owned()),
+    false => Err(format!(
+      "exited with {}: {}",
+      output.status,
+      String::from_utf8_lossy(&output.stderr).trim()
+    )),
+  }
+}
+
+/// Extensions compiled in by the configure arguments, shared ones are only loaded through an ini
+fn expected_modules(configure_args: &[String]) -> Vec<String> {
+  configure_args
+    .iter()
+    .filter_map(|arg| arg.strip_prefix("--enable-").or_else(|| arg.strip_prefix("--with-")))
+    .filter(|arg| arg.split_once('=').is_none_or(|(_, value)| !value.starts_with("shared")))
+    .map(|arg| arg.split('=').next().unwrap_or_default().replace('-', "_"))
+    .filter(|name| EXTENSIONS.split_whitespace().any(|extension| extension == name))
+    .collect()
+}
+
+/// Name `php -m` lists an extension under
+fn module_name(extension: &str) -> &str {
+  match extension {
+    "opcache" => "Zend OPcache",
+    extension => extension,
+  }
+}
+
+/// Verify a fresh install and record the outcome, a failure is an error listing the failed checks
+pub fn verify(source: &SourcePHP, options: &BuildOptions) -> Maybe<()> {
+  let verification = Verification::run(source, options);
+  verification.save(source.src())?;
+
+  let failed = verification.checks.iter().filter(|check| !check.ok).collect::<Vec<_>>();
+  if failed.is_empty() {
+    return Ok(());
+  }
+
+  bail!(
+    "PHP {} was installed but failed verification, it won't be linked until reinstalled with --force\n{}",
+    source.name(),
+    failed
+      .iter()
+      .map(|check| format!("  ❌ {}: {}", check.name, check.detail))
+      .collect::<Vec<_>>()
+      .join("\n")
+  )
+}
+
+#[test]
+fn test_expected_modules() {
+  let args = [
+    "--enable-mbstring",
+    "--with-pdo-mysql=mysqlnd",
+    "--with-curl=shared",
+    "--enable-fpm",
+    "--with-config-file-scan-dir=/tmp/conf.d",
+    "--disable-opcache",
+    "--enable-opcache",
+  ]
+  .map(String::from);
+  assert_eq!(expected_modules(&args), ["mbstring", "pdo_mysql", "opcache"]);
+  assert_eq!(module_name("opcache"), "Zend OPcache");
+}